@@ -0,0 +1,64 @@
+import { FormEvent, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { ChatChannel, ChatMessage, Team } from "lib/bindings";
+import { Button, DropDown, TextInput } from "./InputElements";
+
+export function Chat(props: {
+    messages: ChatMessage[];
+    teams: Team[];
+    canTaunt: boolean;
+    onSend: (channel: ChatChannel, text: string) => void;
+}) {
+    const [channel, setChannel] = useState<ChatChannel>("Team");
+    const [text, setText] = useState<string>("");
+    const { t } = useTranslation();
+
+    const channels: ChatChannel[] = props.canTaunt
+        ? ["Team", "Global", "Taunt"]
+        : ["Team", "Global"];
+
+    const onSubmit = (e: FormEvent<HTMLFormElement>) => {
+        e.preventDefault();
+        if (!text) return;
+
+        props.onSend(channel, text);
+        setText("");
+    };
+
+    return (
+        <div
+            className="fixed right-0 top-0 flex w-80 flex-col gap-2 bg-white/90 p-2"
+            style={{ zIndex: 1000 }}
+        >
+            <div className="flex max-h-48 flex-col overflow-y-auto text-sm">
+                {props.messages.map((msg) => {
+                    const team = props.teams.find((x) => x.id === msg.team_id);
+                    return (
+                        <span key={`${msg.time}-${msg.team_id}-${msg.text}`}>
+                            <span className="text-slate-400">
+                                [{t(msg.channel)}]{" "}
+                            </span>
+                            <span style={{ color: team?.color }}>
+                                {msg.player_name ?? team?.name}:
+                            </span>{" "}
+                            {msg.text}
+                        </span>
+                    );
+                })}
+            </div>
+            <form className="flex gap-2" onSubmit={onSubmit}>
+                <DropDown<ChatChannel>
+                    className="w-28"
+                    items={channels}
+                    onItemChange={setChannel}
+                />
+                <TextInput
+                    onTextChange={setText}
+                    value={text}
+                    placeholder={t("ChatMessage")}
+                />
+                <Button>{t("Send")}</Button>
+            </form>
+        </div>
+    );
+}
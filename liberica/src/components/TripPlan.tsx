@@ -0,0 +1,53 @@
+import { useTranslation } from "react-i18next";
+import { TripLeg, TripPlan } from "lib/bindings";
+import { Button } from "./InputElements";
+
+const formatTime = (time: string | null) =>
+    time
+        ? new Date(time).toLocaleTimeString([], {
+              hour: "2-digit",
+              minute: "2-digit",
+          })
+        : "?";
+
+function Leg(props: { leg: TripLeg }) {
+    const { t } = useTranslation();
+
+    if ("Ride" in props.leg) {
+        const ride = props.leg.Ride;
+        return (
+            <span>
+                {formatTime(ride.departure)} {ride.from.name} →{" "}
+                {formatTime(ride.arrival)} {ride.to.name} (
+                {t("to", { line: ride.line_name, direction: ride.direction })})
+            </span>
+        );
+    }
+    const walk = props.leg.Walk;
+    return (
+        <span className="text-slate-500">
+            {t("WalkTo", { to: walk.to })}
+            {walk.length !== null && ` (${walk.length} m)`}
+        </span>
+    );
+}
+
+export function TripPlanView(props: { plan: TripPlan; onClose: () => void }) {
+    const { t } = useTranslation();
+
+    return (
+        <div
+            className="fixed bottom-16 left-0 flex w-96 flex-col gap-1 bg-white/90 p-2 text-sm"
+            style={{ zIndex: 1000 }}
+        >
+            <span className="font-bold">
+                {formatTime(props.plan.departure)} –{" "}
+                {formatTime(props.plan.arrival)}
+            </span>
+            {props.plan.legs.map((leg, i) => (
+                <Leg leg={leg} key={i} />
+            ))}
+            <Button onClick={props.onClose}>{t("Close")}</Button>
+        </div>
+    );
+}
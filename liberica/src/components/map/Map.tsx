@@ -7,6 +7,7 @@ import {
     LayersControl,
     LayerGroup,
     Circle,
+    Polygon,
     Tooltip,
 } from "react-leaflet";
 import { createContext, useContext, useEffect, useState } from "react";
@@ -14,14 +15,23 @@ import { useTranslation } from "react-i18next";
 import { MrXIcon, TrainIcon, DetectiveIcon } from "components/MapIcons";
 import { Button } from "components/InputElements";
 import { Marker } from "./Marker";
-import { GameState, Stop, TeamState, Train } from "lib/bindings";
-import { getStops } from "lib/api";
-import { getContrastingTextColor } from "lib/util";
+import {
+    GameState,
+    PlayArea,
+    ReachableStop,
+    Stop,
+    TeamState,
+    Train,
+} from "lib/bindings";
+import { getPlayArea, getStops } from "lib/api";
+import { getContrastingTextColor, playAreaPositions } from "lib/util";
 import Style from "style/Map.module.css";
 
 export const GameStateContext = createContext<GameState>({
     teams: [],
     trains: [],
+    reveals: [],
+    mr_x_tickets: [],
 });
 
 const CENTER: [number, number] = [49.0046, 8.403];
@@ -105,14 +115,20 @@ export function Map(
     props: MapProps & {
         onStopClick?: (stop: Stop) => void;
         onTrainClick?: (train: Train) => void;
+        // the stops Mr. X could have reached since his last reveal
+        reachableArea?: ReachableStop[];
     },
 ) {
     const gs = useContext(GameStateContext);
     const [stops, setStops] = useState<Stop[]>([]);
+    const [playArea, setPlayArea] = useState<PlayArea | null>(null);
     useEffect(() => {
         getStops()
             .then(setStops)
             .catch(() => console.warn("failed to get stops"));
+        getPlayArea()
+            .then(setPlayArea)
+            .catch(() => console.warn("failed to get play area"));
     }, []);
 
     return (
@@ -128,6 +144,36 @@ export function Map(
                 {...props.tileProps}
             />
             <LayersControl position="topright">
+                {/* Play area */}
+                {playArea && (
+                    <LayersControl.Overlay checked name="Play area">
+                        <Polygon
+                            positions={playAreaPositions(playArea)}
+                            pathOptions={{ color: "red", fill: false }}
+                        />
+                    </LayersControl.Overlay>
+                )}
+
+                {/* Where Mr. X could be */}
+                {props.reachableArea && props.reachableArea.length > 0 && (
+                    <LayersControl.Overlay checked name="Mr. X">
+                        <LayerGroup>
+                            {props.reachableArea.map((stop) => (
+                                <Circle
+                                    key={stop.stop_id}
+                                    center={[stop.lat, stop.long]}
+                                    pathOptions={{
+                                        stroke: false,
+                                        fillColor: "orange",
+                                        fillOpacity: 0.1 + 0.3 * stop.weight,
+                                    }}
+                                    radius={Math.max(stop.radius, 50)}
+                                />
+                            ))}
+                        </LayerGroup>
+                    </LayersControl.Overlay>
+                )}
+
                 {/* Stops */}
                 <LayersControl.Overlay checked name="Stops">
                     <LayerGroup>
@@ -155,7 +201,7 @@ export function Map(
                         {gs.trains.map((train) => (
                             <TrainMarker
                                 train={train}
-                                key={train.line_id}
+                                key={train.id}
                                 onClick={() => props.onTrainClick?.(train)}
                             />
                         ))}
@@ -1,12 +1,21 @@
 import axios, { AxiosError } from "axios";
-import { Team, Stop, CreateTeam } from "lib/bindings";
+import {
+    Team,
+    Stop,
+    CreateTeam,
+    CreatedTeam,
+    PlayArea,
+    TripQuery,
+    TripPlan,
+} from "lib/bindings";
 import { WebSocketApi } from "./websockets";
 
 const ENDPOINTS = {
     POST_CREATE_TEAM: "/create-team",
-    POST_START_GAME: "/start-game",
     GET_TEAMS: "/teams",
     GET_STOPS: "/stops",
+    GET_PLAY_AREA: "/play-area",
+    POST_TRIPS: "/trips",
     GET_PING: "/ping",
     GET_WS: "/ws",
 };
@@ -58,8 +67,19 @@ export const BASE_URLS = {
 
 export const AXIOS = axios.create({ baseURL: BASE_URLS.HTTP });
 
-export const postCreateTeam = (team: CreateTeam): Promise<Team> =>
-    AXIOS.post(ENDPOINTS.POST_CREATE_TEAM, team);
+export const postCreateTeam = (team: CreateTeam): Promise<CreatedTeam> =>
+    AXIOS.post(ENDPOINTS.POST_CREATE_TEAM, team).then(
+        (data) => data.data as CreatedTeam,
+    );
+
+/**
+ * The join codes of the teams created on this device, so players don't have to type them in again.
+ */
+export const storeJoinCode = (teamId: number, joinCode: string) =>
+    localStorage.setItem(`joinCode-${teamId}`, joinCode);
+
+export const loadJoinCode = (teamId: number): string =>
+    localStorage.getItem(`joinCode-${teamId}`) ?? "";
 
 export const getTeams = (): Promise<Team[]> =>
     AXIOS.get(ENDPOINTS.GET_TEAMS).then((data) => data.data as Team[]);
@@ -67,6 +87,23 @@ export const getTeams = (): Promise<Team[]> =>
 export const getStops = (): Promise<Stop[]> =>
     AXIOS.get(ENDPOINTS.GET_STOPS).then((data) => data.data as Stop[]);
 
+export const getPlayArea = (): Promise<PlayArea | null> =>
+    AXIOS.get(ENDPOINTS.GET_PLAY_AREA).then(
+        (data) => data.data as PlayArea | null,
+    );
+
+/**
+ * Plan trips, only possible for clients that joined a team.
+ * @param session The session token of the websocket connection
+ */
+export const planTrips = (
+    query: TripQuery,
+    session: string,
+): Promise<TripPlan[]> =>
+    AXIOS.post(ENDPOINTS.POST_TRIPS, query, { params: { session } }).then(
+        (data) => data.data as TripPlan[],
+    );
+
 export const serverAlive = (): Promise<boolean> =>
     AXIOS.get(ENDPOINTS.GET_PING)
         .then(() => true)
@@ -1,25 +1,167 @@
 // This file has been generated by Specta. DO NOT EDIT.
 
 /**
- * Information about a tram station.
+ * Settings for a single game.
  */
-export type Stop = { name: string; id: string; lat: number; lon: number }
+export type GameConfig = { reveal_schedule: RevealSchedule; head_start_minutes: number; time_limit_minutes: number | null; capture: CaptureConfig; tickets: TicketConfig | null; observer_delay_seconds: number; train_detection: TrainDetection; position_check: PositionCheckConfig; play_area: PlayArea | null; reveal_out_of_bounds: boolean; reachable_area: boolean }
 
-export type TeamKind = "MrX" | "Detective" | "Observer"
+export type TeamState = { team: Team; long: number; lat: number; on_train?: number | null; tickets?: Tickets | null; players?: Player[] }
+
+/**
+ * When a detective counts as close enough to catch Mr. X.
+ */
+export type CaptureConfig = { radius: number; require_same_train_or_stop: boolean }
+
+export type GameResult = { DetectivesWin: { team_id: number } } | "MrXWins"
+
+/**
+ * The number of tickets of each kind.
+ */
+export type Tickets = { tram: number; s_bahn: number; bus: number; black: number }
+
+export type TripPlan = { departure: string; arrival: string; interchanges: number; legs: TripLeg[] }
 
 export type Team = { id: number; name: string; color: string; kind: TeamKind }
 
-export type ClientMessage = { Position: { long: number; lat: number } } | { SetTeamPosition: { long: number; lat: number } } | { JoinTeam: { team_id: number } } | { EmbarkTrain: { train_id: string } } | "DisembarkTrain" | { Message: string }
+/**
+ * A client connection as listed by the admin API.
+ */
+export type ConnectionInfo = { id: number; team_id: number; player_id: number | null; connected: boolean }
+
+/**
+ * The changes of the game state since the last update. Teams and trains are identified by their id.
+ */
+export type GameStateDelta = { teams: TeamState[]; removed_teams: number[]; trains: Train[]; removed_trains: number[]; reveals: MrXReveal[]; mr_x_tickets: UsedTicket[] }
+
+export type TeamJoinCode = { team_id: number; join_code: string }
+
+/**
+ * The phases of a game. Games start in the lobby and end once they are finished.
+ */
+export type GamePhase = "Lobby" | { Countdown: { until: string } } | "Running" | "Paused" | "Finished"
+
+/**
+ * How positions are checked that a team not on a train couldn't have reached in time.
+ */
+export type PositionCheckConfig = { max_speed_kmh: number | null; reject: boolean }
+
+/**
+ * A stop Mr. X could have reached since his last reveal.
+ */
+export type ReachableStop = { stop_id: string; long: number; lat: number; arrival: string; radius: number; weight: number }
+
+export type Ticket = "Tram" | "SBahn" | "Bus" | "Black"
+
+export type CreatedGame = { game: GameInfo; mr_x_join_code: string }
+
+export type Place = { Stop: { id: string } } | { Position: { long: number; lat: number } }
+
+/**
+ * The area a game is played in, as a GeoJSON `Polygon` or `MultiPolygon` geometry. Coordinates are
+ * `[longitude, latitude]` pairs, the first ring of a polygon is its outline and the others are holes.
+ */
+export type PlayArea = { type: "Polygon"; coordinates: number[][][] } | { type: "MultiPolygon"; coordinates: number[][][][] }
+
+/**
+ * Why a client message was rejected.
+ */
+export type ErrorCode = "InvalidMessage" | "TeamNotFound" | "WrongJoinCode" | "NotInTeam" | { CannotMove: { phase: GamePhase } } | "UnknownTrain" | "TooFarFromTrain" | "ImplausiblePosition" | "StalePosition" | "InaccuratePosition" | "InvalidPosition" | { NoTicket: { ticket: Ticket } } | "NotDetective" | "NotMrX" | { InvalidPhase: { phase: GamePhase } } | "CaptureAlreadyClaimed" | "TooFarFromMrX" | "NoPendingCapture" | "EmptyMessage" | "NotAdmin" | { MessageTooLong: { max_len: number } }
+
+/**
+ * A vehicle on its journey. The id stays the same while the server is running.
+ */
+export type Train = { id: number; long: number; lat: number; line_id: string; line_name: string; direction: string; mode: TransportMode; journey_ref: string; operating_day: string; stop_id: string }
+
+export type ChatMessage = { time: string; channel: ChatChannel; team_id: number; player_name: string | null; text: string }
+
+export type Announcement = { text: string }
+
+/**
+ * A member of a team. The position of a team is derived from the positions of its players.
+ */
+export type Player = { id: number; name: string; team_id: number; long: number; lat: number; located_at: string | null; last_seen: string | null; devices: number }
+
+export type TeamPosition = { long: number; lat: number }
+
+export type CreateGame = { name: string; config: GameConfig | null }
+
+/**
+ * A request to plan trips, e.g. from a detective to where Mr. X was seen.
+ */
+export type TripQuery = { origin: Place; destination: Place; via?: Place[]; departure_time: string | null; arrival_time: string | null; modes?: TransportMode[]; max_interchanges: number | null }
+
+export type UpdateGame = { name: string | null; config: GameConfig | null }
+
+export type TeamKind = "MrX" | "Detective" | "Observer"
+
+/**
+ * A game room as listed by the API.
+ */
+export type GameInfo = { id: number; name: string; phase: GamePhase; result: GameResult | null; config: GameConfig }
+
+export type RevealSchedule = { Interval: { minutes: number } } | { Fixed: { minutes: number[] } }
+
+export type ChatChannel = "Team" | "Global" | "Taunt" | "Announcement"
+
+/**
+ * Why the server took a team off its train.
+ */
+export type DisembarkReason = "LeftTrain" | "JourneyEnded"
+
+export type TripLeg = { Ride: { line_name: string; mode: TransportMode; direction: string; from: LegStop; to: LegStop; departure: string | null; arrival: string | null } } | { Walk: { from: string; to: string; departure: string; arrival: string; length: number | null } }
+
+/**
+ * A position update that implied an impossible speed.
+ */
+export type PositionViolation = { time: string; team_id: number; player_id: number | null; long: number; lat: number; speed_kmh: number; rejected: boolean }
+
+export type ClientResponse = { Session: { token: string; team_id: number } } | { GameState: GameState } | { GameStateDelta: GameStateDelta } | { GamePhase: GamePhase } | { CaptureClaimed: { team_id: number } } | { CaptureRejected: { team_id: number } } | { GameOver: GameResult } | { TrainDetected: { train_id: number } } | { TrainLeft: { train_id: number } } | { Embarked: { train_id: number } } | { LeftPlayArea: { team_id: number } } | { ReturnedToPlayArea: { team_id: number } } | { ReachableArea: { since: string; stops: ReachableStop[] } } | { Disembarked: { train_id: number; reason: DisembarkReason } } | { Chat: ChatMessage } | { Ack: { request_id: number } } | { Error: { request_id: number | null; code: ErrorCode; message: string } }
+
+export type CreateTeamError = "InvalidName" | "MrXAlreadyExists" | "NameAlreadyExists" | "GameAlreadyStarted" | "GameNotFound"
 
-export type CreateTeamError = "InvalidName" | "NameAlreadyExists"
+export type TrainDetection = "Off" | "Suggest" | "Auto"
 
-export type Train = { id: number; long: number; lat: number; line_id: string; line_name: string; direction: string }
+export type TransportMode = "Tram" | "SBahn" | "Bus"
 
-export type TeamState = { team: Team; long: number; lat: number; on_train: string | null }
+export type UsedTicket = { time: string; ticket: Ticket }
+
+export type TicketConfig = { detectives: Tickets; mr_x: Tickets }
+
+/**
+ * A position measured by the device of a player, with the optional fields of the browser's geolocation API.
+ */
+export type PositionUpdate = { long: number; lat: number; accuracy?: number | null; heading?: number | null; speed?: number | null; timestamp?: string | null }
+
+export type GameError = "NotFound" | "TeamNotFound" | "ClientNotFound" | "NameAlreadyExists" | "MrXRequired" | "EmptyMessage" | "Unauthorized" | "InvalidName" | { InvalidPhase: { phase: GamePhase } } | "TripPlanningUnavailable" | { TripPlanningFailed: { message: string } }
+
+/**
+ * Information about a tram station.
+ */
+export type Stop = { name: string; id: string; lat: number; lon: number }
+
+export type LegStop = { id: string; name: string }
+
+/**
+ * A snapshot of Mr. X's position, taken when he is revealed to the detectives.
+ */
+export type MrXReveal = { time: string; long: number; lat: number; on_train?: number | null }
+
+export type GameState = { teams: TeamState[]; trains: Train[]; reveals: MrXReveal[]; mr_x_tickets: UsedTicket[] }
+
+/**
+ * A message with a request id, which the server answers with `Ack` or `Error`.
+ * Plain `ClientMessage`s are accepted too, but rejections of them are only logged.
+ */
+export type ClientRequest = { request_id: number | null; message: ClientMessage }
+
+/**
+ * The response to creating a team. Players need the join code to join the team.
+ */
+export type CreatedTeam = { team: Team; join_code: string }
 
 export type CreateTeam = { name: string; color: string; kind: TeamKind }
 
-export type ClientResponse = { GameState: GameState }
+export type ClientMessage = { Position: PositionUpdate } | { SetTeamPosition: { long: number; lat: number } } | { JoinTeam: { team_id: number; join_code: string; player_name: string | null } } | "LeaveTeam" | { EmbarkTrain: { train_id: number; black_ticket: boolean } } | "DisembarkTrain" | { Message: { channel: ChatChannel; text: string } } | { DeltaUpdates: { enabled: boolean } } | "Resync" | { Observe: { admin_token: string } } | "ClaimCapture" | "ConfirmCapture" | "RejectCapture"
 
-export type GameState = { teams: TeamState[]; trains: Train[] }
+export type UpdateTeam = { name: string | null; color: string | null }
 
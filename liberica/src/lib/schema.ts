@@ -13,13 +13,35 @@ const Team = Record({
     kind: TeamKind,
 });
 
+const Tickets = Record({
+    tram: Number,
+    s_bahn: Number,
+    bus: Number,
+    black: Number,
+});
+
+const Player = Record({
+    id: Number,
+    name: String,
+    team_id: Number,
+    long: Number,
+    lat: Number,
+    located_at: Union(String, Null),
+    last_seen: Union(String, Null),
+    devices: Number,
+});
+
 const TeamState = Record({
     team: Team,
     long: Number,
     lat: Number,
-    on_train: Union(String, Null),
+    on_train: Union(Number, Null).optional(),
+    tickets: Union(Tickets, Null).optional(),
+    players: Array(Player).optional(),
 });
 
+const TransportMode = Union(Literal("Tram"), Literal("SBahn"), Literal("Bus"));
+
 const Train = Record({
     id: Number,
     long: Number,
@@ -27,11 +49,29 @@ const Train = Record({
     line_id: String,
     line_name: String,
     direction: String,
+    mode: TransportMode,
+    journey_ref: String,
+    operating_day: String,
+    stop_id: String,
+});
+
+const MrXReveal = Record({
+    time: String,
+    long: Number,
+    lat: Number,
+    on_train: Union(Number, Null).optional(),
+});
+
+const UsedTicket = Record({
+    time: String,
+    ticket: Union(TransportMode, Literal("Black")),
 });
 
 const GameState = Record({
     teams: Array(TeamState),
     trains: Array(Train),
+    reveals: Array(MrXReveal),
+    mr_x_tickets: Array(UsedTicket),
 });
 
 export default {
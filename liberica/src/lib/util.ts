@@ -1,3 +1,5 @@
+import { GameState, GameStateDelta, PlayArea } from "lib/bindings";
+
 export const extractPos = (item: {
     lat: number;
     long: number;
@@ -21,3 +23,45 @@ export const getContrastingTextColor = (bg: string) => {
 export const clamp = (x: number, min: number, max: number) => {
     return Math.min(Math.max(x, min), max);
 };
+
+/**
+ * The polygons of the play area as `[lat, lon]` positions for leaflet.
+ */
+export const playAreaPositions = (
+    area: PlayArea,
+): [number, number][][][] => {
+    const polygons =
+        area.type === "Polygon" ? [area.coordinates] : area.coordinates;
+    return polygons.map((polygon) =>
+        polygon.map((ring) =>
+            ring.map(([lon, lat]): [number, number] => [lat, lon]),
+        ),
+    );
+};
+
+/**
+ * Apply the changes of a delta update to the game state.
+ */
+export const applyDelta = (gs: GameState, delta: GameStateDelta): GameState => {
+    const changedTeams = new Set(delta.teams.map((ts) => ts.team.id));
+    const changedTrains = new Set(delta.trains.map((train) => train.id));
+
+    return {
+        teams: gs.teams
+            .filter(
+                (ts) =>
+                    !changedTeams.has(ts.team.id) &&
+                    !delta.removed_teams.includes(ts.team.id),
+            )
+            .concat(delta.teams),
+        trains: gs.trains
+            .filter(
+                (train) =>
+                    !changedTrains.has(train.id) &&
+                    !delta.removed_trains.includes(train.id),
+            )
+            .concat(delta.trains),
+        reveals: gs.reveals.concat(delta.reveals),
+        mr_x_tickets: gs.mr_x_tickets.concat(delta.mr_x_tickets),
+    };
+};
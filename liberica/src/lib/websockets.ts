@@ -1,4 +1,9 @@
-import { ClientMessage, ClientResponse } from "lib/bindings";
+import {
+    ClientMessage,
+    ClientRequest,
+    ClientResponse,
+    ErrorCode,
+} from "lib/bindings";
 
 export type Keys<T> = T extends T ? keyof T : never;
 export type Concrete<T, K extends Keys<T>> = T extends { [P in K]: infer V }
@@ -24,6 +29,32 @@ export const WEBSOCKET_CODES = {
     SERVICE_RESTART: 1012,
 };
 
+/**
+ * The server rejected a request
+ */
+export class WSRequestError extends Error {
+    constructor(
+        public code: ErrorCode,
+        message: string,
+    ) {
+        super(message);
+    }
+}
+
+/**
+ * The connection closed before the server answered a request
+ */
+export class WSClosedError extends Error {
+    constructor() {
+        super("The connection to the server was closed");
+    }
+}
+
+interface PendingRequest {
+    resolve: () => void;
+    reject: (error: WSRequestError | WSClosedError) => void;
+}
+
 /* eslint-disable @typescript-eslint/no-invalid-void-type */
 export type WSEvent =
     | { Disconnect: CloseEvent }
@@ -35,6 +66,10 @@ export class WebSocketApi {
 
     private connection!: WebSocket;
     private endpoint!: string;
+    /** The token to resume the session after a reconnect */
+    private sessionToken?: string;
+    private nextRequestId = 0;
+    private pendingRequests = new Map<number, PendingRequest>();
 
     private handlers: WSHandlerMap<ClientResponse> = {};
     private metaHandlers: WSHandlerMap<WSEvent> = {};
@@ -43,6 +78,11 @@ export class WebSocketApi {
         this.connect(endpoint);
     }
 
+    /** The token of the session, e.g. to authenticate HTTP requests */
+    public get session(): string | undefined {
+        return this.sessionToken;
+    }
+
     public reconnect(force = false) {
         // Don't try to reconnect if there is a connection already
         if (this.connection.readyState === this.connection.OPEN && force)
@@ -55,9 +95,22 @@ export class WebSocketApi {
     public connect(endpoint: string) {
         console.log("Connecting to", endpoint);
         this.endpoint = endpoint;
-        this.connection = new WebSocket(endpoint);
+        const url = new URL(endpoint);
+        if (this.sessionToken)
+            url.searchParams.set("session", this.sessionToken);
+        this.connection = new WebSocket(url);
+        // a replaced connection may close after the new one was opened
+        const pendingRequests = new Map<number, PendingRequest>();
+        this.pendingRequests = pendingRequests;
         this.connection.onerror = (e) => this.metaHandlers["Error"]?.(e);
-        this.connection.onclose = (e) => this.metaHandlers["Disconnect"]?.(e);
+        this.connection.onclose = (e) => {
+            // the server won't answer them anymore
+            pendingRequests.forEach((request) =>
+                request.reject(new WSClosedError()),
+            );
+            pendingRequests.clear();
+            this.metaHandlers["Disconnect"]?.(e);
+        };
         this.connection.onopen = () => this.metaHandlers["Connect"]?.();
         this.connection.onmessage = (e) => {
             if (typeof e.data !== "string") return;
@@ -78,6 +131,20 @@ export class WebSocketApi {
 
     private handleMessage(msg: ClientResponse) {
         this.lastMessage = new Date();
+        if ("Session" in msg) this.sessionToken = msg.Session.token;
+        if ("Ack" in msg) {
+            this.pendingRequests.get(msg.Ack.request_id)?.resolve();
+            this.pendingRequests.delete(msg.Ack.request_id);
+            return;
+        }
+        if ("Error" in msg && msg.Error.request_id !== null) {
+            const { request_id, code, message } = msg.Error;
+            this.pendingRequests
+                .get(request_id)
+                ?.reject(new WSRequestError(code, message));
+            this.pendingRequests.delete(request_id);
+            return;
+        }
         for (const key in msg) {
             const handler = key as Keys<ClientResponse>;
             if (!this.handlers[handler])
@@ -108,6 +175,24 @@ export class WebSocketApi {
         this.connection.send(JSON.stringify(msg));
     }
 
+    /**
+     * Send a message and wait for the server to accept or reject it.
+     * The promise is rejected with a {@link WSRequestError} if the server rejects the message,
+     * and with a {@link WSClosedError} if the connection closes before the server answered.
+     */
+    public request(msg: ClientMessage): Promise<void> {
+        const requestId = this.nextRequestId++;
+        const request: ClientRequest = { request_id: requestId, message: msg };
+        return new Promise((resolve, reject) => {
+            if (this.connection.readyState !== this.connection.OPEN) {
+                reject(new WSClosedError());
+                return;
+            }
+            this.pendingRequests.set(requestId, { resolve, reject });
+            this.connection.send(JSON.stringify(request));
+        });
+    }
+
     public disconnect() {
         this.connection.close();
     }
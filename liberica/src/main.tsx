@@ -3,6 +3,7 @@ import { Game } from "page/Game";
 import { Home } from "page/Home";
 import { Replay } from "page/Replay";
 import { Admin } from "page/Admin";
+import { Observe } from "page/Observe";
 import ReactDOM from "react-dom/client";
 import { BrowserRouter, Route, Routes } from "react-router-dom";
 import i18n from "i18next";
@@ -41,6 +42,7 @@ ReactDOM.createRoot(rootElement).render(
             <Route path="/game" element={<Game />} />
             <Route path="/replay" element={<Replay />} />
             <Route path="/admin" element={<Admin />} />
+            <Route path="/observe" element={<Observe />} />
         </Routes>
     </BrowserRouter>,
 );
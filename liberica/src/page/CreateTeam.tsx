@@ -1,5 +1,5 @@
 import { Button, DropDown, TextInput } from "components/InputElements";
-import { defaultErrorHandler, postCreateTeam } from "lib/api";
+import { defaultErrorHandler, postCreateTeam, storeJoinCode } from "lib/api";
 import { TeamKind } from "lib/bindings";
 import { FormEvent, useState } from "react";
 import { useTranslation } from "react-i18next";
@@ -28,7 +28,10 @@ export function CreateTeam() {
 
         setLoading(true);
         postCreateTeam({ color, name, kind })
-            .then(() => navigate("/"))
+            .then(({ team, join_code }) => {
+                storeJoinCode(team.id, join_code);
+                navigate("/");
+            })
             .catch(defaultErrorHandler)
             .finally(() => setLoading(false));
     };
@@ -48,7 +51,7 @@ export function CreateTeam() {
 
                 <DropDown<TeamKind>
                     onItemChange={setKind}
-                    items={["Detective", "MrX", "Observer"]}
+                    items={["Detective", "Observer"]}
                 />
 
                 <div className="flex justify-between gap-3">
@@ -1,46 +1,166 @@
 import { GameStateContext, Map } from "components/map/Map";
-import { createWebSocketConnection } from "lib/api";
-import { GameState, Team, Train } from "lib/bindings";
-import { WebSocketApi } from "lib/websockets";
+import { createWebSocketConnection, planTrips } from "lib/api";
+import { applyDelta } from "lib/util";
+import {
+    ChatChannel,
+    ChatMessage,
+    GameState,
+    ReachableStop,
+    Team,
+    Train,
+    TripPlan,
+} from "lib/bindings";
+import { WebSocketApi, WSRequestError } from "lib/websockets";
 import { useEffect, useState } from "react";
 import { useLocation } from "react-router-dom";
 import { HomeButton, Navbar } from "components/Navbar";
 import { Button } from "components/InputElements";
+import { Chat } from "components/Chat";
+import { TripPlanView } from "components/TripPlan";
 import { useTranslation } from "react-i18next";
 
 export function Game() {
     const [ws, setWS] = useState<WebSocketApi>();
-    const [gs, setGameState] = useState<GameState>({ teams: [], trains: [] });
-    const [embarkedTrain, setEmbarkedTrain] = useState<Train>();
-    const team = useLocation().state as Team | undefined; // this is how Home passes the team
+    const [gs, setGameState] = useState<GameState>({
+        teams: [],
+        trains: [],
+        reveals: [],
+        mr_x_tickets: [],
+    });
+    // the train the server detected the team on
+    const [detectedTrainId, setDetectedTrainId] = useState<number>();
+    const [notice, setNotice] = useState<
+        "LeftTrain" | "JourneyEnded" | "OutOfBounds"
+    >();
+    // the stops Mr. X could have reached since his last reveal
+    const [reachableArea, setReachableArea] = useState<ReachableStop[]>([]);
+    // the route to where Mr. X was seen last
+    const [tripPlan, setTripPlan] = useState<TripPlan>();
+    const [error, setError] = useState<string>();
+    const [messages, setMessages] = useState<ChatMessage[]>([]);
+    // this is how Home passes the team
+    const { team, joinCode, playerName } =
+        (useLocation().state as {
+            team: Team;
+            joinCode: string;
+            playerName: string;
+        } | null) ?? {};
     const { t } = useTranslation();
 
+    const teamState = gs.teams.find((ts) => ts.team.id === team?.id);
+    const onTrain = teamState?.on_train;
+    const lastReveal = gs.reveals[gs.reveals.length - 1];
+    const embarkedTrain = gs.trains.find((train) => train.id === onTrain);
+    const detectedTrain = gs.trains.find(
+        (train) => train.id === detectedTrainId,
+    );
+
+    const showError = (err: unknown) => {
+        if (err instanceof WSRequestError) setError(err.message);
+    };
+
     function disembark() {
         if (!team) return;
 
-        setEmbarkedTrain(undefined);
+        setNotice(undefined);
         ws?.send("DisembarkTrain");
     }
 
+    function sendMessage(channel: ChatChannel, text: string) {
+        setError(undefined);
+        ws?.request({ Message: { channel, text } }).catch(showError);
+    }
+
     function embark(train: Train) {
         if (!team) return;
 
-        setEmbarkedTrain(train);
-        ws?.send({ EmbarkTrain: { train_id: train.line_id } });
+        setError(undefined);
+        setNotice(undefined);
+        setDetectedTrainId(undefined);
+        ws?.request({
+            EmbarkTrain: { train_id: train.id, black_ticket: false },
+        }).catch(showError);
+    }
+
+    function routeToMrX() {
+        const session = ws?.session;
+        if (!teamState || !lastReveal || !session) return;
+
+        setError(undefined);
+        planTrips(
+            {
+                origin: {
+                    Position: { long: teamState.long, lat: teamState.lat },
+                },
+                destination: {
+                    Position: { long: lastReveal.long, lat: lastReveal.lat },
+                },
+                departure_time: null,
+                arrival_time: null,
+                max_interchanges: null,
+            },
+            session,
+        )
+            .then((plans) =>
+                plans.length > 0
+                    ? setTripPlan(plans[0])
+                    : setError(t("NoTrip")),
+            )
+            .catch(() => setError(t("NoTrip")));
     }
 
     useEffect(() => {
         const socket = createWebSocketConnection();
 
         socket
-            .registerEvent("Connect", () => setWS(socket))
+            .registerEvent("Connect", () => {
+                socket.send({ DeltaUpdates: { enabled: true } });
+                setWS(socket);
+            })
             .registerEvent("Error", (e) => {
                 setWS(undefined);
                 console.error("WebSocket connection closed uncleanly:", e);
                 setTimeout(() => socket.reconnect(), 1000);
             });
 
-        socket.register("GameState", (gs) => setGameState(gs));
+        socket.register("GameState", (gs) => {
+            setGameState(gs);
+            // the game was reset
+            if (gs.reveals.length === 0) setReachableArea([]);
+        });
+        socket.register("GameStateDelta", (delta) =>
+            setGameState((gs) => applyDelta(gs, delta)),
+        );
+        socket.register("TrainDetected", ({ train_id }) =>
+            setDetectedTrainId(train_id),
+        );
+        socket.register("TrainLeft", () => setNotice("LeftTrain"));
+        socket.register("Embarked", () => setDetectedTrainId(undefined));
+        socket.register("Disembarked", ({ reason }) =>
+            setNotice(reason === "JourneyEnded" ? "JourneyEnded" : undefined),
+        );
+        socket.register("LeftPlayArea", () => setNotice("OutOfBounds"));
+        socket.register("ReturnedToPlayArea", () =>
+            setNotice((notice) =>
+                notice === "OutOfBounds" ? undefined : notice,
+            ),
+        );
+        socket.register("ReachableArea", ({ stops }) =>
+            setReachableArea(stops),
+        );
+        socket.register("Chat", (msg) =>
+            setMessages((messages) =>
+                // the history is sent again after reconnects and joining a team
+                messages.some(
+                    (m) =>
+                        m.time === msg.time &&
+                        m.team_id === msg.team_id &&
+                        m.text === msg.text,
+                )
+                    ? messages
+                    : [...messages, msg],
+            ),
+        );
 
         return () => {
             setWS(undefined);
@@ -50,8 +170,16 @@ export function Game() {
 
     useEffect(() => {
         if (!team) return;
-        ws?.send({ JoinTeam: { team_id: team.id } });
-    }, [ws, team]);
+        ws?.request({
+            JoinTeam: {
+                team_id: team.id,
+                join_code: joinCode ?? "",
+                player_name: playerName || null,
+            },
+        }).catch((err: unknown) => {
+            if (err instanceof WSRequestError) setError(err.message);
+        });
+    }, [ws, team, joinCode, playerName]);
 
     useEffect(() => {
         if (window.isSecureContext) {
@@ -60,6 +188,10 @@ export function Game() {
                     Position: {
                         lat: pos.coords.latitude,
                         long: pos.coords.longitude,
+                        accuracy: pos.coords.accuracy,
+                        heading: pos.coords.heading,
+                        speed: pos.coords.speed,
+                        timestamp: new Date(pos.timestamp).toISOString(),
                     },
                 });
             });
@@ -84,18 +216,51 @@ export function Game() {
                         }
                     }}
                     onTrainClick={embark}
+                    reachableArea={reachableArea}
                 />
             </GameStateContext.Provider>
 
+            {tripPlan && (
+                <TripPlanView
+                    plan={tripPlan}
+                    onClose={() => setTripPlan(undefined)}
+                />
+            )}
+
+            {team && (
+                <Chat
+                    messages={messages}
+                    teams={gs.teams.map((ts) => ts.team)}
+                    canTaunt={team.kind === "MrX"}
+                    onSend={sendMessage}
+                />
+            )}
+
             <Navbar>
                 <HomeButton />
 
+                {error && <span className="text-red-500">{error}</span>}
+
                 {embarkedTrain && (
                     <span>
                         {embarkedTrain.line_name} {embarkedTrain.direction}
                     </span>
                 )}
 
+                {detectedTrain && !embarkedTrain && (
+                    <Button onClick={() => embark(detectedTrain)}>
+                        {t("EmbarkDetected", {
+                            line: detectedTrain.line_name,
+                        })}
+                    </Button>
+                )}
+
+                {notice && <span>{t(notice)}</span>}
+
+                {team?.kind === "Detective" && lastReveal && (
+                    <Button onClick={routeToMrX}>{t("RouteToMrX")}</Button>
+                )}
+
                 <Button disabled={!embarkedTrain} onClick={disembark}>
                     {t("Disembark")}
                 </Button>
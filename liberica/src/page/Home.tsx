@@ -1,6 +1,6 @@
-import { Button } from "components/InputElements";
+import { Button, TextInput } from "components/InputElements";
 import { TeamCard } from "components/TeamCard";
-import { getTeams } from "lib/api";
+import { getTeams, loadJoinCode } from "lib/api";
 import { Team } from "lib/bindings";
 import { useEffect, useState } from "react";
 import { useTranslation } from "react-i18next";
@@ -9,6 +9,10 @@ import { Link, useNavigate } from "react-router-dom";
 export function Home() {
     const [teams, setTeams] = useState<Team[]>([]);
     const [selected, setSelected] = useState<number | undefined>();
+    const [joinCode, setJoinCode] = useState<string>("");
+    const [playerName, setPlayerName] = useState<string>(
+        localStorage.getItem("playerName") ?? "",
+    );
     const navigate = useNavigate();
     const { t } = useTranslation();
 
@@ -25,7 +29,10 @@ export function Home() {
         if (selected === undefined) {
             return;
         }
-        navigate("/game", { state: teams[selected] });
+        localStorage.setItem("playerName", playerName);
+        navigate("/game", {
+            state: { team: teams[selected], joinCode, playerName },
+        });
     };
 
     return (
@@ -38,12 +45,28 @@ export function Home() {
                             key={team.id}
                             team={team}
                             selected={selected === index}
-                            onClick={() => setSelected(index)}
+                            onClick={() => {
+                                setSelected(index);
+                                setJoinCode(loadJoinCode(team.id));
+                            }}
                         />
                     ))}
                 </div>
+                <TextInput
+                    onTextChange={setPlayerName}
+                    trim="all"
+                    value={playerName}
+                    placeholder={t("PlayerName")}
+                />
+                <TextInput
+                    onTextChange={setJoinCode}
+                    trim="all"
+                    value={joinCode}
+                    placeholder={t("JoinCode")}
+                    autoCapitalize="characters"
+                />
                 <Button
-                    disabled={selected === undefined}
+                    disabled={selected === undefined || joinCode === ""}
                     type="button"
                     onClick={process}
                 >
@@ -0,0 +1,112 @@
+import { GameStateContext, Map } from "components/map/Map";
+import { Button, TextInput } from "components/InputElements";
+import { HomeButton, Navbar } from "components/Navbar";
+import { createWebSocketConnection } from "lib/api";
+import { applyDelta } from "lib/util";
+import { GameState } from "lib/bindings";
+import { WebSocketApi, WSRequestError } from "lib/websockets";
+import { FormEvent, useEffect, useState } from "react";
+import { useTranslation } from "react-i18next";
+
+/**
+ * Shows the whole game including Mr. X, e.g. on a screen at the base. Needs the admin token.
+ */
+export function Observe() {
+    const [ws, setWS] = useState<WebSocketApi>();
+    const [adminToken, setAdminToken] = useState<string>("");
+    const [observing, setObserving] = useState(false);
+    const [error, setError] = useState<string>();
+    // the teams outside of the play area
+    const [outOfBounds, setOutOfBounds] = useState<number[]>([]);
+    const [gs, setGameState] = useState<GameState>({
+        teams: [],
+        trains: [],
+        reveals: [],
+        mr_x_tickets: [],
+    });
+    const { t } = useTranslation();
+
+    useEffect(() => {
+        const socket = createWebSocketConnection();
+
+        socket
+            .registerEvent("Connect", () => {
+                socket.send({ DeltaUpdates: { enabled: true } });
+                setWS(socket);
+            })
+            .registerEvent("Error", (e) => {
+                setWS(undefined);
+                console.error("WebSocket connection closed uncleanly:", e);
+                setTimeout(() => socket.reconnect(), 1000);
+            });
+
+        socket.register("GameState", (gs) => setGameState(gs));
+        socket.register("GameStateDelta", (delta) =>
+            setGameState((gs) => applyDelta(gs, delta)),
+        );
+        socket.register("LeftPlayArea", ({ team_id }) =>
+            setOutOfBounds((ids) => [...ids, team_id]),
+        );
+        socket.register("ReturnedToPlayArea", ({ team_id }) =>
+            setOutOfBounds((ids) => ids.filter((id) => id !== team_id)),
+        );
+
+        return () => {
+            setWS(undefined);
+            socket.disconnect();
+        };
+    }, []);
+
+    const onSubmit = (e: FormEvent<HTMLFormElement>) => {
+        e.preventDefault();
+
+        ws?.request({ Observe: { admin_token: adminToken } })
+            .then(() => {
+                setError(undefined);
+                setObserving(true);
+            })
+            .catch((err: unknown) => {
+                if (err instanceof WSRequestError) setError(err.message);
+            });
+    };
+
+    if (!observing) {
+        return (
+            <form
+                className="flex h-screen items-center justify-center"
+                onSubmit={onSubmit}
+            >
+                <div className="container flex w-80 flex-col gap-4 bg-white p-8">
+                    <h2 className="text-lg font-semibold">{t("Observe")}</h2>
+                    <TextInput
+                        type="password"
+                        onTextChange={setAdminToken}
+                        placeholder={t("AdminToken")}
+                    />
+                    {error && <span className="text-red-500">{error}</span>}
+                    <Button disabled={!ws || !adminToken}>{t("Observe")}</Button>
+                </div>
+            </form>
+        );
+    }
+
+    return (
+        <div className="flex h-max w-max flex-col">
+            <GameStateContext.Provider value={gs}>
+                <Map />
+            </GameStateContext.Provider>
+
+            <Navbar>
+                <HomeButton />
+
+                {gs.teams
+                    .filter((ts) => outOfBounds.includes(ts.team.id))
+                    .map((ts) => (
+                        <span key={ts.team.id} className="text-red-500">
+                            {t("TeamOutOfBounds", { team: ts.team.name })}
+                        </span>
+                    ))}
+            </Navbar>
+        </div>
+    );
+}
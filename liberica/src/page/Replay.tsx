@@ -27,7 +27,12 @@ function parseCSV(data: string): Entry[] {
             const [time, state] = line.split(", ", 2);
             return {
                 time: new Date(time),
-                state: schema.GameState.check(JSON.parse(state)),
+                // logs from older versions don't contain the reveal history
+                state: schema.GameState.check({
+                    reveals: [],
+                    mr_x_tickets: [],
+                    ...(JSON.parse(state) as object),
+                }),
             };
         });
 }
@@ -79,7 +84,12 @@ interface ReplayState {
 
 export function Replay() {
     const { t } = useTranslation();
-    const [gs, setGameState] = useState<GameState>({ teams: [], trains: [] });
+    const [gs, setGameState] = useState<GameState>({
+        teams: [],
+        trains: [],
+        reveals: [],
+        mr_x_tickets: [],
+    });
 
     // UI state
     const [running, setRunning] = useState(false);
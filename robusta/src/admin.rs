@@ -0,0 +1,351 @@
+use std::fs;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+use crate::room::Room;
+use crate::ws_message::{
+    Announcement, ConnectionInfo, CreateGame, CreatedGame, GameError, GameInfo, GamePhase, PositionViolation, Team,
+    TeamJoinCode, TeamPosition, UpdateGame, UpdateTeam,
+};
+use crate::{rooms_dir, GameResponse, InputMessage, RoomQuery, SharedState};
+
+lazy_static! {
+    /// The token needed for admin requests. Admin requests are rejected if it isn't set.
+    static ref ADMIN_TOKEN: Option<String> = dotenv::var("ADMIN_TOKEN").ok().filter(|token| !token.is_empty());
+}
+
+/// Whether the request is authorized by an `Authorization: Bearer <ADMIN_TOKEN>` header.
+pub fn is_admin(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(is_admin_token)
+}
+
+pub fn is_admin_token(token: &str) -> bool {
+    ADMIN_TOKEN
+        .as_ref()
+        .is_some_and(|admin_token| constant_time_eq(admin_token.as_bytes(), token.as_bytes()))
+}
+
+/// Compare the bytes without stopping at the first difference, so the time doesn't tell how much of a guess was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub fn is_enabled() -> bool {
+    ADMIN_TOKEN.is_some()
+}
+
+/// A change of a room by the game master.
+#[derive(Debug)]
+pub enum AdminCommand {
+    ListJoinCodes,
+    RotateJoinCode { team_id: u32 },
+    UpdateTeam { team_id: u32, update: UpdateTeam },
+    DeleteTeam { team_id: u32 },
+    SetTeamPosition { team_id: u32, long: f32, lat: f32 },
+    StartGame,
+    PauseGame,
+    ResumeGame,
+    EndGame,
+    ResetGame,
+    ListConnections,
+    ListViolations,
+    Kick { client_id: u32 },
+    Announce { text: String },
+}
+
+#[derive(Debug)]
+pub enum AdminResponse {
+    JoinCodes(Vec<TeamJoinCode>),
+    JoinCode(TeamJoinCode),
+    Team(Team),
+    Connections(Vec<ConnectionInfo>),
+    Violations(Vec<PositionViolation>),
+    Done,
+}
+
+impl IntoResponse for AdminResponse {
+    fn into_response(self) -> Response {
+        match self {
+            Self::JoinCodes(join_codes) => Json(join_codes).into_response(),
+            Self::JoinCode(join_code) => Json(join_code).into_response(),
+            Self::Team(team) => Json(team).into_response(),
+            Self::Connections(connections) => Json(connections).into_response(),
+            Self::Violations(violations) => Json(violations).into_response(),
+            Self::Done => StatusCode::NO_CONTENT.into_response(),
+        }
+    }
+}
+
+pub type AdminResult = Result<AdminResponse, (StatusCode, GameError)>;
+
+/// An admin command for the game loop, which sends the result back.
+#[derive(Debug)]
+pub struct AdminRequest {
+    pub room_id: Option<u32>,
+    pub command: AdminCommand,
+    pub respond: oneshot::Sender<AdminResult>,
+}
+
+/// Execute an admin command in the game loop.
+pub async fn execute(room: &mut Room, command: AdminCommand, time: DateTime<Utc>) -> AdminResult {
+    let not_found = |err| (StatusCode::NOT_FOUND, err);
+    let invalid = |err| (StatusCode::UNPROCESSABLE_ENTITY, err);
+    let phase = room.game.phase;
+    let invalid_phase = |_| (StatusCode::CONFLICT, GameError::InvalidPhase { phase });
+    match command {
+        AdminCommand::ListJoinCodes => return Ok(AdminResponse::JoinCodes(room.join_codes())),
+        AdminCommand::RotateJoinCode { team_id } => {
+            if !room.teams.iter().any(|ts| ts.team.id == team_id) {
+                return Err(not_found(GameError::TeamNotFound));
+            }
+            let join_code = room.rotate_join_code(team_id);
+            return Ok(AdminResponse::JoinCode(TeamJoinCode { team_id, join_code }));
+        }
+        AdminCommand::UpdateTeam { team_id, update } => {
+            return match room.update_team(team_id, update) {
+                Ok(team) => Ok(AdminResponse::Team(team)),
+                Err(GameError::TeamNotFound) => Err(not_found(GameError::TeamNotFound)),
+                Err(err) => Err(invalid(err)),
+            };
+        }
+        AdminCommand::DeleteTeam { team_id } => room.delete_team(team_id).map_err(|err| match err {
+            GameError::TeamNotFound => not_found(err),
+            _ => invalid(err),
+        })?,
+        AdminCommand::SetTeamPosition { team_id, long, lat } => {
+            room.force_position(team_id, long, lat).map_err(not_found)?
+        }
+        AdminCommand::StartGame => room.start(time).map_err(invalid_phase)?,
+        AdminCommand::PauseGame => room.game.pause(time).map_err(invalid_phase)?,
+        AdminCommand::ResumeGame => room.game.resume(time).map_err(invalid_phase)?,
+        AdminCommand::EndGame => room.game.finish(time, None).map_err(invalid_phase)?,
+        AdminCommand::ResetGame => room.reset(),
+        AdminCommand::ListConnections => return Ok(AdminResponse::Connections(room.connection_infos())),
+        AdminCommand::ListViolations => return Ok(AdminResponse::Violations(room.game.violations.clone())),
+        AdminCommand::Kick { client_id } => room.kick(client_id).map_err(not_found)?,
+        AdminCommand::Announce { text } => room.announce(&text, time).await.map_err(invalid)?,
+    }
+    Ok(AdminResponse::Done)
+}
+
+/// Queue the command in the game loop and wait for its result.
+async fn request(state: SharedState, room_id: Option<u32>, command: AdminCommand) -> Response {
+    let sender = state.lock().await.game_logic_sender.clone();
+    let (respond, result) = oneshot::channel();
+    let request = AdminRequest {
+        room_id,
+        command,
+        respond,
+    };
+    if sender.send(InputMessage::Admin(request)).await.is_err() {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    match result.await {
+        Ok(Ok(response)) => response.into_response(),
+        Ok(Err((status, err))) => (status, Json(err)).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn auth<B>(request: Request<B>, next: Next<B>) -> Response {
+    if !is_admin(request.headers()) {
+        return (StatusCode::UNAUTHORIZED, Json(GameError::Unauthorized)).into_response();
+    }
+    next.run(request).await
+}
+
+async fn list_join_codes(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::ListJoinCodes).await
+}
+
+async fn rotate_join_code(
+    Path(team_id): Path<u32>,
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+) -> Response {
+    request(state, query.game, AdminCommand::RotateJoinCode { team_id }).await
+}
+
+async fn update_team(
+    Path(team_id): Path<u32>,
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+    Json(update): Json<UpdateTeam>,
+) -> Response {
+    request(state, query.game, AdminCommand::UpdateTeam { team_id, update }).await
+}
+
+async fn delete_team(
+    Path(team_id): Path<u32>,
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+) -> Response {
+    request(state, query.game, AdminCommand::DeleteTeam { team_id }).await
+}
+
+async fn set_team_position(
+    Path(team_id): Path<u32>,
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+    Json(TeamPosition { long, lat }): Json<TeamPosition>,
+) -> Response {
+    request(state, query.game, AdminCommand::SetTeamPosition { team_id, long, lat }).await
+}
+
+async fn start_game(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::StartGame).await
+}
+
+async fn pause_game(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::PauseGame).await
+}
+
+async fn resume_game(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::ResumeGame).await
+}
+
+async fn end_game(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::EndGame).await
+}
+
+async fn reset_game(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::ResetGame).await
+}
+
+async fn list_connections(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::ListConnections).await
+}
+
+async fn list_violations(Query(query): Query<RoomQuery>, State(state): State<SharedState>) -> Response {
+    request(state, query.game, AdminCommand::ListViolations).await
+}
+
+async fn kick(
+    Path(client_id): Path<u32>,
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+) -> Response {
+    request(state, query.game, AdminCommand::Kick { client_id }).await
+}
+
+async fn announce(
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+    Json(Announcement { text }): Json<Announcement>,
+) -> Response {
+    request(state, query.game, AdminCommand::Announce { text }).await
+}
+
+async fn create_game(State(state): State<SharedState>, Json(game): Json<CreateGame>) -> GameResponse<CreatedGame> {
+    let mut state = state.lock().await;
+    let name = game.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(GameError::InvalidName)));
+    }
+
+    let id = state.room_id_gen.next();
+    let config = game.config.unwrap_or_else(|| state.default_config.clone());
+    let mut room = Room::new(id, name.to_owned(), config);
+    room.save(rooms_dir());
+    info!("Created game {} ({})", id, room.name);
+    let created = CreatedGame {
+        game: room.info(),
+        mr_x_join_code: room.mr_x_join_code().unwrap_or_default().to_owned(),
+    };
+    state.rooms.push(room);
+    Ok(Json(created))
+}
+
+async fn update_game(
+    Path(id): Path<u32>,
+    State(state): State<SharedState>,
+    Json(update): Json<UpdateGame>,
+) -> GameResponse<GameInfo> {
+    let mut state = state.lock().await;
+    let room = state
+        .room_mut(id)
+        .ok_or((StatusCode::NOT_FOUND, Json(GameError::NotFound)))?;
+    if let Some(name) = update.name {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(GameError::InvalidName)));
+        }
+        room.name = name.to_owned();
+    }
+    if let Some(config) = update.config {
+        // changing the rules of a running game would be unfair
+        if room.game.phase != GamePhase::Lobby {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(GameError::InvalidPhase { phase: room.game.phase }),
+            ));
+        }
+        room.config = config;
+    }
+    Ok(Json(room.info()))
+}
+
+async fn delete_game(
+    Path(id): Path<u32>,
+    State(state): State<SharedState>,
+) -> Result<StatusCode, (StatusCode, Json<GameError>)> {
+    let mut state = state.lock().await;
+    let Some(index) = state.rooms.iter().position(|room| room.id == id) else {
+        return Err((StatusCode::NOT_FOUND, Json(GameError::NotFound)));
+    };
+    // dropping the room closes the connections of its clients
+    state.rooms.remove(index);
+    if let Err(err) = fs::remove_file(Room::path(rooms_dir(), id)) {
+        warn!("failed to remove game file of game {}: {}", id, err);
+    }
+    info!("Deleted game {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The routes of the game master, which need the admin token.
+pub fn router() -> Router<SharedState> {
+    Router::new()
+        .route("/games", post(create_game))
+        .route("/games/:id", put(update_game).delete(delete_game))
+        .route("/join-codes", get(list_join_codes))
+        .route("/teams/:id", delete(delete_team).patch(update_team))
+        .route("/teams/:id/rotate-join-code", post(rotate_join_code))
+        .route("/teams/:id/position", post(set_team_position))
+        .route("/start-game", post(start_game))
+        .route("/pause-game", post(pause_game))
+        .route("/resume-game", post(resume_game))
+        .route("/end-game", post(end_game))
+        .route("/reset-game", post(reset_game))
+        .route("/connections", get(list_connections))
+        .route("/connections/:id", delete(kick))
+        .route("/violations", get(list_violations))
+        .route("/announcements", post(announce))
+        .route_layer(middleware::from_fn(auth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"secret", b""));
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::point::Point;
+
+/// How far (in degrees, about a meter) a point may be away from an edge of the area to count as on it.
+const EDGE_TOLERANCE: f32 = 1e-5;
+
+/// The area a game is played in, as a GeoJSON `Polygon` or `MultiPolygon` geometry. Coordinates are
+/// `[longitude, latitude]` pairs, the first ring of a polygon is its outline and the others are holes.
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum PlayArea {
+    Polygon { coordinates: Vec<Vec<[f32; 2]>> },
+    MultiPolygon { coordinates: Vec<Vec<Vec<[f32; 2]>>> },
+}
+
+impl PlayArea {
+    pub fn contains(&self, point: Point) -> bool {
+        match self {
+            Self::Polygon { coordinates } => polygon_contains(coordinates, point),
+            Self::MultiPolygon { coordinates } => coordinates.iter().any(|polygon| polygon_contains(polygon, point)),
+        }
+    }
+}
+
+/// Whether the point is inside the outline of the polygon and outside of its holes. Points on the outline or on the
+/// edge of a hole count as inside, so teams walking along the border aren't told they left.
+fn polygon_contains(rings: &[Vec<[f32; 2]>], point: Point) -> bool {
+    let Some((outline, holes)) = rings.split_first() else {
+        return false;
+    };
+    if rings.iter().any(|ring| ring_edge_contains(ring, point)) {
+        return true;
+    }
+    ring_contains(outline, point) && !holes.iter().any(|hole| ring_contains(hole, point))
+}
+
+/// Whether the point is on one of the edges of the ring, up to `EDGE_TOLERANCE`.
+fn ring_edge_contains(ring: &[[f32; 2]], point: Point) -> bool {
+    let (x, y) = (point.longitude, point.latitude);
+    ring.iter().enumerate().any(|(i, &[x1, y1])| {
+        let [x2, y2] = ring[(i + 1) % ring.len()];
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let length = dx * dx + dy * dy;
+        // the nearest point of the edge
+        let t = if length > 0. {
+            (((x - x1) * dx + (y - y1) * dy) / length).clamp(0., 1.)
+        } else {
+            0.
+        };
+        f32::hypot(x - (x1 + t * dx), y - (y1 + t * dy)) <= EDGE_TOLERANCE
+    })
+}
+
+/// Ray casting: a point is inside of a ring if a ray from it crosses the edges of the ring an odd number of times.
+fn ring_contains(ring: &[[f32; 2]], point: Point) -> bool {
+    let (x, y) = (point.longitude, point.latitude);
+    let mut inside = false;
+    for (i, &[x1, y1]) in ring.iter().enumerate() {
+        let [x2, y2] = ring[(i + 1) % ring.len()];
+        if (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(longitude: f32, latitude: f32) -> Point {
+        Point { latitude, longitude }
+    }
+
+    fn square(left: f32, bottom: f32, size: f32) -> Vec<[f32; 2]> {
+        vec![
+            [left, bottom],
+            [left + size, bottom],
+            [left + size, bottom + size],
+            [left, bottom + size],
+            [left, bottom],
+        ]
+    }
+
+    #[test]
+    fn test_polygon() {
+        let area = PlayArea::Polygon {
+            coordinates: vec![square(8.3, 49.0, 0.1)],
+        };
+        assert!(area.contains(point(8.35, 49.05)));
+        assert!(!area.contains(point(8.25, 49.05)));
+        assert!(!area.contains(point(8.45, 49.05)));
+        assert!(!area.contains(point(8.35, 48.95)));
+        assert!(!area.contains(point(8.35, 49.15)));
+
+        assert!(!PlayArea::Polygon { coordinates: vec![] }.contains(point(8.35, 49.05)));
+    }
+
+    #[test]
+    fn test_hole() {
+        let area = PlayArea::Polygon {
+            coordinates: vec![square(8.3, 49.0, 0.1), square(8.33, 49.03, 0.04)],
+        };
+        assert!(area.contains(point(8.31, 49.01)));
+        assert!(!area.contains(point(8.35, 49.05)));
+        assert!(!area.contains(point(8.5, 49.05)));
+    }
+
+    #[test]
+    fn test_boundary() {
+        let area = PlayArea::Polygon {
+            coordinates: vec![square(8.3, 49.0, 0.1), square(8.33, 49.03, 0.04)],
+        };
+        // on the edges and corners of the outline
+        for (longitude, latitude) in [
+            (8.3, 49.05),
+            (8.4, 49.05),
+            (8.35, 49.0),
+            (8.35, 49.1),
+            (8.3, 49.0),
+            (8.4, 49.1),
+        ] {
+            assert!(area.contains(point(longitude, latitude)), "{} {}", longitude, latitude);
+        }
+        // on the edges of the hole
+        assert!(area.contains(point(8.33, 49.05)));
+        assert!(area.contains(point(8.37, 49.07)));
+        // just outside of the outline and inside of the hole
+        assert!(!area.contains(point(8.4001, 49.05)));
+        assert!(!area.contains(point(8.3301, 49.05)));
+
+        let triangle = PlayArea::Polygon {
+            coordinates: vec![vec![[8.3, 49.0], [8.4, 49.0], [8.3, 49.1], [8.3, 49.0]]],
+        };
+        assert!(triangle.contains(point(8.35, 49.05)));
+        assert!(!triangle.contains(point(8.351, 49.051)));
+    }
+
+    #[test]
+    fn test_multi_polygon() {
+        let area = PlayArea::MultiPolygon {
+            coordinates: vec![
+                vec![square(8.3, 49.0, 0.1), square(8.33, 49.03, 0.04)],
+                vec![square(8.5, 49.0, 0.1)],
+            ],
+        };
+        assert!(area.contains(point(8.31, 49.01)));
+        assert!(area.contains(point(8.55, 49.05)));
+        assert!(!area.contains(point(8.35, 49.05)));
+        assert!(!area.contains(point(8.45, 49.05)));
+    }
+
+    #[test]
+    fn test_geojson() {
+        let area: PlayArea = serde_json::from_str(
+            r#"{ "type": "Polygon", "coordinates": [[[8.35, 48.99], [8.45, 48.99], [8.45, 49.03], [8.35, 49.03], [8.35, 48.99]]] }"#,
+        )
+        .unwrap();
+        assert!(area.contains(point(8.4, 49.0)));
+        let area: PlayArea = serde_json::from_str(
+            r#"{ "type": "MultiPolygon", "coordinates": [[[[8.35, 48.99], [8.45, 48.99], [8.45, 49.03]]]] }"#,
+        )
+        .unwrap();
+        assert!(area.contains(point(8.44, 49.0)));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::config::CaptureConfig;
+use crate::kvv;
+use crate::ws_message::TeamState;
+
+/// Whether the detective team is close enough to Mr. X to catch him.
+pub fn can_capture(config: &CaptureConfig, detective: &TeamState, mr_x: &TeamState) -> bool {
+    if detective.position().distance(mr_x.position()) > config.radius {
+        return false;
+    }
+    if !config.require_same_train_or_stop {
+        return true;
+    }
+
+    let same_train = detective.on_train.is_some() && detective.on_train == mr_x.on_train;
+    let same_stop = match (
+        kvv::nearest_stop(detective.position()),
+        kvv::nearest_stop(mr_x.position()),
+    ) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    };
+    same_train || same_stop
+}
@@ -0,0 +1,169 @@
+use std::fs;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::area::PlayArea;
+use crate::ws_message::{TeamKind, Tickets};
+
+/// Settings for a single game.
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct GameConfig {
+    /// When Mr. X's position is revealed to the detectives.
+    pub reveal_schedule: RevealSchedule,
+    /// How long only Mr. X may move after the game was started.
+    pub head_start_minutes: u32,
+    /// Mr. X wins if he wasn't caught after this many minutes of game time. `None` means no time limit.
+    pub time_limit_minutes: Option<u32>,
+    pub capture: CaptureConfig,
+    /// The tickets each team starts with, `None` means that trains can be used without limit.
+    pub tickets: Option<TicketConfig>,
+    /// How many seconds the game state shown to observers lags behind, so they can't give Mr. X away.
+    pub observer_delay_seconds: u32,
+    /// What to do when a team seems to get on or off a train without telling.
+    pub train_detection: TrainDetection,
+    pub position_check: PositionCheckConfig,
+    /// The area the teams have to stay in, `None` if they may go anywhere.
+    pub play_area: Option<PlayArea>,
+    /// Whether the detectives see Mr. X while he is outside of the play area.
+    pub reveal_out_of_bounds: bool,
+    /// Whether the detectives are told where Mr. X could have gone since his last reveal.
+    pub reachable_area: bool,
+}
+
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug)]
+pub struct TicketConfig {
+    pub detectives: Tickets,
+    pub mr_x: Tickets,
+}
+
+impl TicketConfig {
+    /// The initial tickets of a team, observers don't get any.
+    pub fn for_kind(&self, kind: TeamKind) -> Option<Tickets> {
+        match kind {
+            TeamKind::MrX => Some(self.mr_x.clone()),
+            TeamKind::Detective => Some(self.detectives.clone()),
+            TeamKind::Observer => None,
+        }
+    }
+}
+
+#[derive(specta::Type, Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub enum TrainDetection {
+    Off,
+    /// Suggest embarking or disembarking to the team.
+    #[default]
+    Suggest,
+    /// Embark and disembark detectives automatically. Mr. X only gets suggestions, he may want to use a black ticket.
+    Auto,
+}
+
+/// When a detective counts as close enough to catch Mr. X.
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CaptureConfig {
+    /// The maximum distance between the detective and Mr. X in meters.
+    pub radius: f32,
+    /// Whether the detective also has to be on the same train or at the same stop as Mr. X.
+    pub require_same_train_or_stop: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            radius: 50.0,
+            require_same_train_or_stop: false,
+        }
+    }
+}
+
+/// How positions are checked that a team not on a train couldn't have reached in time.
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct PositionCheckConfig {
+    /// The maximum speed in km/h of a team that isn't on a train, `None` disables the check.
+    pub max_speed_kmh: Option<f32>,
+    /// Whether implausible positions are ignored, otherwise they are only recorded.
+    pub reject: bool,
+}
+
+impl Default for PositionCheckConfig {
+    fn default() -> Self {
+        Self {
+            max_speed_kmh: Some(30.0),
+            reject: true,
+        }
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            reveal_schedule: RevealSchedule::default(),
+            head_start_minutes: 10,
+            time_limit_minutes: Some(120),
+            capture: CaptureConfig::default(),
+            tickets: None,
+            observer_delay_seconds: 0,
+            train_detection: TrainDetection::default(),
+            position_check: PositionCheckConfig::default(),
+            play_area: None,
+            reveal_out_of_bounds: false,
+            reachable_area: true,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Load the config from `path`, falling back to the default config if the file is missing or invalid.
+    pub fn load(path: &str) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|err| {
+            warn!("Invalid game config {}: {}", path, err);
+            Self::default()
+        })
+    }
+
+    pub fn head_start(&self) -> Duration {
+        Duration::from_secs(self.head_start_minutes as u64 * 60)
+    }
+
+    pub fn observer_delay(&self) -> Duration {
+        Duration::from_secs(self.observer_delay_seconds as u64)
+    }
+
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit_minutes
+            .map(|minutes| Duration::from_secs(minutes as u64 * 60))
+    }
+}
+
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum RevealSchedule {
+    /// Reveal Mr. X every `minutes` minutes.
+    Interval { minutes: u32 },
+    /// Reveal Mr. X at the given minutes after the start of the game, in ascending order, e.g. `[3, 8, 13, 18]`.
+    Fixed { minutes: Vec<u32> },
+}
+
+impl Default for RevealSchedule {
+    fn default() -> Self {
+        Self::Interval { minutes: 10 }
+    }
+}
+
+impl RevealSchedule {
+    /// The game time of the `n`-th reveal (starting at 0), or `None` if there are no more reveals.
+    pub fn reveal_time(&self, n: usize) -> Option<Duration> {
+        let minutes = match self {
+            Self::Interval { minutes: 0 } => return None,
+            Self::Interval { minutes } => (n as u64 + 1) * *minutes as u64,
+            Self::Fixed { minutes } => *minutes.get(n)? as u64,
+        };
+        Some(Duration::from_secs(minutes * 60))
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ws_message::{ClientResponse, GameState, GameStateDelta};
+
+/// How many updates are sent as deltas before the full state is sent again.
+const KEYFRAME_INTERVAL: u32 = 60;
+
+/// Encodes the game states sent to a client as deltas to the previous state, if the client asked for it.
+#[derive(Debug, Default)]
+pub struct DeltaEncoder {
+    pub enabled: bool,
+    /// The last state the client got, `None` if the next update has to be a keyframe.
+    last: Option<GameState>,
+    since_keyframe: u32,
+}
+
+impl DeltaEncoder {
+    /// Send the full state with the next update.
+    pub fn resync(&mut self) {
+        self.last = None;
+    }
+
+    /// The response to send for the new state, `None` if nothing changed.
+    pub fn encode(&mut self, state: GameState) -> Option<ClientResponse> {
+        if !self.enabled {
+            return Some(ClientResponse::GameState(state));
+        }
+        let delta = self
+            .last
+            .as_ref()
+            .filter(|_| self.since_keyframe < KEYFRAME_INTERVAL)
+            .and_then(|last| diff(last, &state));
+        self.last = Some(state.clone());
+        let Some(delta) = delta else {
+            self.since_keyframe = 0;
+            return Some(ClientResponse::GameState(state));
+        };
+        self.since_keyframe += 1;
+        (!delta.is_empty()).then_some(ClientResponse::GameStateDelta(delta))
+    }
+}
+
+/// The changes from `old` to `new`, `None` if they can't be expressed as a delta.
+fn diff(old: &GameState, new: &GameState) -> Option<GameStateDelta> {
+    // reveals and tickets are only ever appended, unless the game was reset
+    let (Some(new_reveals), Some(new_tickets)) = (
+        new.reveals.get(old.reveals.len()..),
+        new.mr_x_tickets.get(old.mr_x_tickets.len()..),
+    ) else {
+        return None;
+    };
+    let (teams, removed_teams) = diff_by_id(&old.teams, &new.teams, |ts| ts.team.id);
+    let (trains, removed_trains) = diff_by_id(&old.trains, &new.trains, |train| train.id);
+    Some(GameStateDelta {
+        teams,
+        removed_teams,
+        trains,
+        removed_trains,
+        reveals: new_reveals.to_vec(),
+        mr_x_tickets: new_tickets.to_vec(),
+    })
+}
+
+/// The items that were added or changed, and the ids of the items that were removed.
+fn diff_by_id<T: Clone + PartialEq>(old: &[T], new: &[T], id: impl Fn(&T) -> u32) -> (Vec<T>, Vec<u32>) {
+    let old_by_id: HashMap<u32, &T> = old.iter().map(|item| (id(item), item)).collect();
+    let new_ids: HashSet<u32> = new.iter().map(&id).collect();
+    let changed = new
+        .iter()
+        .filter(|&item| old_by_id.get(&id(item)) != Some(&item))
+        .cloned()
+        .collect();
+    let removed = old.iter().map(&id).filter(|id| !new_ids.contains(id)).collect();
+    (changed, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::ws_message::{MrXReveal, Team, TeamState, Ticket, Train, UsedTicket};
+
+    fn team(id: u32, long: f32) -> TeamState {
+        TeamState {
+            team: Team {
+                id,
+                ..Default::default()
+            },
+            long,
+            ..Default::default()
+        }
+    }
+
+    fn train(id: u32, long: f32) -> Train {
+        Train {
+            id,
+            long,
+            ..Default::default()
+        }
+    }
+
+    fn reveal(minutes: u32) -> MrXReveal {
+        MrXReveal {
+            time: Utc.with_ymd_and_hms(2023, 10, 11, 12, minutes, 0).unwrap(),
+            long: 8.4,
+            lat: 49.0,
+            on_train: None,
+        }
+    }
+
+    fn ticket(minutes: u32) -> UsedTicket {
+        UsedTicket {
+            time: Utc.with_ymd_and_hms(2023, 10, 11, 12, minutes, 0).unwrap(),
+            ticket: Ticket::Tram,
+        }
+    }
+
+    /// Apply a delta like the clients do, changed teams and trains are moved to the end.
+    fn apply(state: &GameState, delta: &GameStateDelta) -> GameState {
+        let teams = state
+            .teams
+            .iter()
+            .filter(|ts| !delta.teams.iter().any(|changed| changed.team.id == ts.team.id))
+            .filter(|ts| !delta.removed_teams.contains(&ts.team.id))
+            .chain(&delta.teams);
+        let trains = state
+            .trains
+            .iter()
+            .filter(|train| !delta.trains.iter().any(|changed| changed.id == train.id))
+            .filter(|train| !delta.removed_trains.contains(&train.id))
+            .chain(&delta.trains);
+        GameState {
+            teams: teams.cloned().collect(),
+            trains: trains.cloned().collect(),
+            reveals: [&state.reveals[..], &delta.reveals].concat(),
+            mr_x_tickets: [&state.mr_x_tickets[..], &delta.mr_x_tickets].concat(),
+        }
+    }
+
+    fn assert_same_state(mut left: GameState, mut right: GameState) {
+        for state in [&mut left, &mut right] {
+            state.teams.sort_by_key(|ts| ts.team.id);
+            state.trains.sort_by_key(|train| train.id);
+        }
+        assert_eq!(left.teams, right.teams);
+        assert_eq!(left.trains, right.trains);
+        assert_eq!(left.reveals, right.reveals);
+        assert_eq!(left.mr_x_tickets, right.mr_x_tickets);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let old = GameState {
+            teams: vec![team(1, 8.4), team(2, 8.4)],
+            trains: vec![train(1, 8.4), train(2, 8.4)],
+            reveals: vec![reveal(10)],
+            mr_x_tickets: vec![ticket(5)],
+        };
+        let new = GameState {
+            teams: vec![team(3, 8.4), team(1, 8.5)],
+            trains: vec![train(2, 8.4), train(1, 8.5), train(3, 8.4)],
+            reveals: vec![reveal(10), reveal(20)],
+            mr_x_tickets: vec![ticket(5), ticket(12), ticket(15)],
+        };
+
+        let delta = diff(&old, &new).unwrap();
+        assert_eq!(delta.teams, [team(3, 8.4), team(1, 8.5)]);
+        assert_eq!(delta.removed_teams, [2]);
+        assert_eq!(delta.trains, [train(1, 8.5), train(3, 8.4)]);
+        assert_eq!(delta.removed_trains, [] as [u32; 0]);
+        assert_eq!(delta.reveals, [reveal(20)]);
+        assert_eq!(delta.mr_x_tickets, [ticket(12), ticket(15)]);
+        assert_same_state(apply(&old, &delta), new.clone());
+
+        assert!(diff(&new, &new).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reset() {
+        let old = GameState {
+            reveals: vec![reveal(10)],
+            mr_x_tickets: vec![ticket(5)],
+            ..Default::default()
+        };
+        // reveals and tickets are gone after a reset, which a delta can't express
+        assert!(diff(&old, &GameState::default()).is_none());
+        let new = GameState {
+            mr_x_tickets: vec![ticket(5)],
+            ..Default::default()
+        };
+        assert!(diff(&old, &new).is_none());
+    }
+
+    #[test]
+    fn test_encoder() {
+        let mut encoder = DeltaEncoder {
+            enabled: true,
+            ..Default::default()
+        };
+        let state = |i: u32| GameState {
+            trains: vec![train(1, i as f32)],
+            reveals: (0..i / 10).map(reveal).collect(),
+            ..Default::default()
+        };
+
+        let mut client_state = GameState::default();
+        for i in 0..=2 * KEYFRAME_INTERVAL {
+            // a keyframe first and after every `KEYFRAME_INTERVAL` deltas
+            let keyframe = i % (KEYFRAME_INTERVAL + 1) == 0;
+            match encoder.encode(state(i)) {
+                Some(ClientResponse::GameState(state)) if keyframe => client_state = state,
+                Some(ClientResponse::GameStateDelta(delta)) if !keyframe => client_state = apply(&client_state, &delta),
+                response => panic!("unexpected response {:?} to update {}", response, i),
+            }
+            assert_same_state(client_state.clone(), state(i));
+        }
+
+        // nothing changed
+        assert!(encoder.encode(state(2 * KEYFRAME_INTERVAL)).is_none());
+        // the game was reset
+        assert!(matches!(
+            encoder.encode(GameState::default()),
+            Some(ClientResponse::GameState(_))
+        ));
+        encoder.resync();
+        assert!(matches!(
+            encoder.encode(GameState::default()),
+            Some(ClientResponse::GameState(_))
+        ));
+
+        encoder.enabled = false;
+        assert!(matches!(
+            encoder.encode(GameState::default()),
+            Some(ClientResponse::GameState(_))
+        ));
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::point::Point;
+use crate::ws_message::Train;
+
+/// The time span over which the movements of a team and a train are compared.
+const WINDOW: Duration = Duration::from_secs(15);
+/// How far a team on a train may be away from it in meters, GPS is inaccurate inside of trains.
+const MAX_DISTANCE: f32 = 60.0;
+/// How far a train has to move within the window to be told apart from pedestrians and trains waiting at a stop.
+const MIN_MOVEMENT: f32 = 50.0;
+/// For how many consecutive ticks a team has to move like a train to count as being on it.
+const EMBARK_TICKS: u32 = 6;
+/// How far a team has to be away from its train in meters to count as having left it.
+const LEAVE_DISTANCE: f32 = 150.0;
+/// For how many consecutive ticks a team has to be away from its train to count as having left it.
+const LEAVE_TICKS: u32 = 10;
+
+/// Recent positions, oldest first.
+#[derive(Debug, Default)]
+struct Track(VecDeque<(DateTime<Utc>, Point)>);
+
+impl Track {
+    fn push(&mut self, time: DateTime<Utc>, position: Point) {
+        self.0.push_back((time, position));
+        // keep one position from before the window, to know where the window started
+        while self.0.get(1).is_some_and(|&(at, _)| elapsed(at, time) > WINDOW) {
+            self.0.pop_front();
+        }
+    }
+
+    fn latest(&self) -> Option<(DateTime<Utc>, Point)> {
+        self.0.back().copied()
+    }
+
+    /// The position at `time`, i.e. the last one recorded until then.
+    fn at(&self, time: DateTime<Utc>) -> Option<Point> {
+        self.0
+            .iter()
+            .rev()
+            .find(|&&(at, _)| at <= time)
+            .map(|&(_, position)| position)
+    }
+}
+
+fn elapsed(since: DateTime<Utc>, time: DateTime<Utc>) -> Duration {
+    (time - since).to_std().unwrap_or_default()
+}
+
+/// The recent positions of all trains.
+#[derive(Debug, Default)]
+pub struct TrainTracks(HashMap<u32, Track>);
+
+impl TrainTracks {
+    pub fn record(&mut self, time: DateTime<Utc>, trains: &[Train]) {
+        for train in trains {
+            self.0.entry(train.id).or_default().push(time, train.position());
+        }
+        // forget trains that finished their journey
+        self.0
+            .retain(|_, track| track.latest().is_some_and(|(at, _)| elapsed(at, time) <= WINDOW));
+    }
+
+    fn at(&self, train_id: u32, time: DateTime<Utc>) -> Option<Point> {
+        self.0.get(&train_id)?.at(time)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detection {
+    /// The team moves like the train, but isn't on it.
+    Embarked(u32),
+    /// The team is on the train, but moves away from it.
+    Left(u32),
+}
+
+/// Detects a team getting on or off a train by comparing its measured positions with the positions of the trains.
+#[derive(Debug, Default)]
+pub struct TrainDetector {
+    track: Track,
+    /// The train the team moves like, and for how many ticks it did.
+    candidate: Option<(u32, u32)>,
+    /// For how many ticks the team has been away from its train.
+    away_ticks: u32,
+    /// The last detection, so it is reported only once.
+    reported: Option<Detection>,
+}
+
+impl TrainDetector {
+    /// Record a position measured by the players of the team.
+    pub fn record(&mut self, time: DateTime<Utc>, position: Point) {
+        self.track.push(time, position);
+    }
+
+    /// Compare the team with the trains, returns a detection once the team got on or off a train.
+    pub fn detect(&mut self, time: DateTime<Utc>, on_train: Option<u32>, trains: &TrainTracks) -> Option<Detection> {
+        let start = time - chrono::Duration::from_std(WINDOW).unwrap_or_default();
+        let positions = self
+            .track
+            .latest()
+            .filter(|&(at, _)| elapsed(at, time) <= WINDOW)
+            .zip(self.track.at(start));
+        let Some(((_, position), start_position)) = positions else {
+            // the players stopped sending their positions
+            self.candidate = None;
+            self.away_ticks = 0;
+            return None;
+        };
+
+        let detection = match on_train {
+            None => {
+                self.away_ticks = 0;
+                let train_id = best_match(position, start_position, time, start, trains);
+                self.candidate = match (self.candidate, train_id) {
+                    (Some((candidate, ticks)), Some(train_id)) if candidate == train_id => Some((train_id, ticks + 1)),
+                    (_, train_id) => train_id.map(|train_id| (train_id, 1)),
+                };
+                self.candidate
+                    .filter(|&(_, ticks)| ticks >= EMBARK_TICKS)
+                    .map(|(train_id, _)| Detection::Embarked(train_id))
+            }
+            Some(train_id) => {
+                self.candidate = None;
+                // the train may be gone already
+                let train_position = trains.at(train_id, time)?;
+                if position.distance(train_position) > LEAVE_DISTANCE {
+                    self.away_ticks += 1;
+                } else {
+                    self.away_ticks = 0;
+                }
+                (self.away_ticks >= LEAVE_TICKS).then_some(Detection::Left(train_id))
+            }
+        };
+
+        if detection == self.reported {
+            return None;
+        }
+        self.reported = detection;
+        detection
+    }
+}
+
+/// The nearest train that stayed close to the team during the whole window and moved the same way.
+fn best_match(
+    position: Point,
+    start_position: Point,
+    time: DateTime<Utc>,
+    start: DateTime<Utc>,
+    trains: &TrainTracks,
+) -> Option<u32> {
+    trains
+        .0
+        .iter()
+        .filter_map(|(&train_id, track)| Some((train_id, track.at(time)?, track.at(start)?)))
+        .filter(|&(_, train_position, train_start)| {
+            // where the team would be if it moved exactly like the train
+            let expected = Point {
+                latitude: start_position.latitude + train_position.latitude - train_start.latitude,
+                longitude: start_position.longitude + train_position.longitude - train_start.longitude,
+            };
+            train_start.distance(train_position) >= MIN_MOVEMENT
+                && position.distance(train_position) <= MAX_DISTANCE
+                && start_position.distance(train_start) <= MAX_DISTANCE
+                && position.distance(expected) <= MAX_DISTANCE
+        })
+        .min_by(|(_, a, _), (_, b, _)| position.distance(*a).total_cmp(&position.distance(*b)))
+        .map(|(train_id, _, _)| train_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const TRAIN_ID: u32 = 1;
+    /// The speed of the train in degrees of latitude per second, about 11 m/s.
+    const TRAIN_SPEED: f32 = 0.0001;
+
+    fn time(seconds: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 10, 11, 12, 0, 0).unwrap() + chrono::Duration::seconds(seconds.into())
+    }
+
+    fn train_position(seconds: u32) -> Point {
+        Point {
+            latitude: 49.0 + seconds as f32 * TRAIN_SPEED,
+            longitude: 8.4,
+        }
+    }
+
+    /// Move the train north for `seconds` and the team to `team_position(second)`, one tick per second. Returns the
+    /// detections by tick.
+    fn run(seconds: u32, on_train: Option<u32>, team_position: impl Fn(u32) -> Point) -> Vec<(u32, Detection)> {
+        let mut tracks = TrainTracks::default();
+        let mut detector = TrainDetector::default();
+        (0..seconds)
+            .filter_map(|second| {
+                let position = train_position(second);
+                let train = Train {
+                    id: TRAIN_ID,
+                    long: position.longitude,
+                    lat: position.latitude,
+                    ..Default::default()
+                };
+                tracks.record(time(second), &[train]);
+                detector.record(time(second), team_position(second));
+                let detection = detector.detect(time(second), on_train, &tracks)?;
+                Some((second, detection))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_embark() {
+        let window = WINDOW.as_secs() as u32;
+        // the team is compared once it was tracked for a whole window, and then has to follow the train for a while
+        let detections = run(60, None, train_position);
+        assert_eq!(detections, [(window + EMBARK_TICKS - 1, Detection::Embarked(TRAIN_ID))]);
+
+        // a team following the train for a moment only
+        let detections = run(60, None, |second| {
+            if second % EMBARK_TICKS == 0 {
+                train_position(second + 10)
+            } else {
+                train_position(second)
+            }
+        });
+        assert!(detections.is_empty());
+
+        // a team waiting at the stop the train left
+        assert!(run(60, None, |_| train_position(0)).is_empty());
+    }
+
+    #[test]
+    fn test_leave() {
+        let window = WINDOW.as_secs() as u32;
+        let away = |second| Point {
+            latitude: train_position(second).latitude,
+            longitude: 8.41,
+        };
+        // the team stays on the train at first, then it is far away from it
+        let left = window + 5;
+        let detections = run(60, Some(TRAIN_ID), |second| {
+            if second < left {
+                train_position(second)
+            } else {
+                away(second)
+            }
+        });
+        assert_eq!(detections, [(left + LEAVE_TICKS - 1, Detection::Left(TRAIN_ID))]);
+
+        // GPS jumps for less than `LEAVE_TICKS` ticks
+        let detections = run(60, Some(TRAIN_ID), |second| {
+            if second % LEAVE_TICKS == 0 {
+                train_position(second)
+            } else {
+                away(second)
+            }
+        });
+        assert!(detections.is_empty());
+    }
+}
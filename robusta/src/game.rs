@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ws_message::{GamePhase, GameResult, MrXReveal, PositionViolation, TeamKind, UsedTicket};
+
+/// A phase transition that is not possible from the current phase.
+#[derive(Debug)]
+pub struct InvalidTransition;
+
+/// The record of a single game.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Game {
+    pub phase: GamePhase,
+    /// Since when the game is running, `None` if it is not running.
+    running_since: Option<DateTime<Utc>>,
+    /// The game time played before `running_since`, i.e. the game time excluding pauses.
+    played: Duration,
+    /// The rest of Mr. X's head start if the game was paused during the countdown.
+    countdown_left: Option<Duration>,
+    pub reveals: Vec<MrXReveal>,
+    pub mr_x_tickets: Vec<UsedTicket>,
+    /// The detective team whose capture claim waits for Mr. X's confirmation.
+    pub pending_capture: Option<u32>,
+    /// The outcome of the game, `None` while it isn't finished or if it was ended without a winner.
+    pub result: Option<GameResult>,
+    /// The position updates that implied impossible speeds, for the game master to review.
+    pub violations: Vec<PositionViolation>,
+}
+
+impl Game {
+    /// The game time that has passed at `time`, excluding the head start and pauses.
+    pub fn elapsed(&self, time: DateTime<Utc>) -> Duration {
+        let running = self
+            .running_since
+            .and_then(|since| (time - since).to_std().ok())
+            .unwrap_or_default();
+        self.played + running
+    }
+
+    /// Start the game from the lobby, giving Mr. X a head start of `head_start`.
+    pub fn start(&mut self, time: DateTime<Utc>, head_start: Duration) -> Result<(), InvalidTransition> {
+        if self.phase != GamePhase::Lobby {
+            return Err(InvalidTransition);
+        }
+        self.phase = GamePhase::Countdown {
+            until: time + chrono::Duration::from_std(head_start).unwrap_or_default(),
+        };
+        self.update(time, None);
+        Ok(())
+    }
+
+    /// Pause the running game or Mr. X's head start, which continues where it stopped when the game is resumed.
+    pub fn pause(&mut self, time: DateTime<Utc>) -> Result<(), InvalidTransition> {
+        match self.phase {
+            GamePhase::Running => {
+                self.played = self.elapsed(time);
+                self.running_since = None;
+            }
+            GamePhase::Countdown { until } => {
+                self.countdown_left = Some((until - time).to_std().unwrap_or_default());
+            }
+            _ => return Err(InvalidTransition),
+        }
+        self.phase = GamePhase::Paused;
+        Ok(())
+    }
+
+    pub fn resume(&mut self, time: DateTime<Utc>) -> Result<(), InvalidTransition> {
+        if self.phase != GamePhase::Paused {
+            return Err(InvalidTransition);
+        }
+        match self.countdown_left.take() {
+            Some(left) => {
+                self.phase = GamePhase::Countdown {
+                    until: time + chrono::Duration::from_std(left).unwrap_or_default(),
+                };
+                self.update(time, None);
+            }
+            None => {
+                self.running_since = Some(time);
+                self.phase = GamePhase::Running;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(&mut self, time: DateTime<Utc>, result: Option<GameResult>) -> Result<(), InvalidTransition> {
+        if self.phase == GamePhase::Finished {
+            return Err(InvalidTransition);
+        }
+        self.played = self.elapsed(time);
+        self.running_since = None;
+        self.countdown_left = None;
+        self.pending_capture = None;
+        self.phase = GamePhase::Finished;
+        self.result = result;
+        Ok(())
+    }
+
+    /// Advance time based transitions, i.e. end the countdown once the head start is over and end the game once the
+    /// time limit has expired.
+    pub fn update(&mut self, time: DateTime<Utc>, time_limit: Option<Duration>) {
+        if let GamePhase::Countdown { until } = self.phase {
+            if time >= until {
+                self.running_since = Some(until);
+                self.phase = GamePhase::Running;
+            }
+        }
+        if self.phase == GamePhase::Running && time_limit.is_some_and(|limit| self.elapsed(time) >= limit) {
+            let _ = self.finish(time, Some(GameResult::MrXWins));
+        }
+    }
+
+    /// Whether teams of the given kind may currently move, i.e. send positions or use trains.
+    pub fn can_move(&self, kind: TeamKind) -> bool {
+        match self.phase {
+            GamePhase::Lobby | GamePhase::Running => true,
+            GamePhase::Countdown { .. } => kind == TeamKind::MrX,
+            GamePhase::Paused | GamePhase::Finished => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const HEAD_START: Duration = Duration::from_secs(600);
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 10, 11, 12, 0, 0).unwrap() + chrono::Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn test_pause_running() {
+        let mut game = Game::default();
+        assert!(game.pause(time(0)).is_err());
+        game.start(time(0), HEAD_START).unwrap();
+        game.update(time(600), None);
+        assert_eq!(game.phase, GamePhase::Running);
+
+        game.pause(time(700)).unwrap();
+        assert_eq!(game.phase, GamePhase::Paused);
+        assert!(game.pause(time(700)).is_err());
+        assert_eq!(game.elapsed(time(1000)), Duration::from_secs(100));
+
+        game.resume(time(1000)).unwrap();
+        assert_eq!(game.phase, GamePhase::Running);
+        assert_eq!(game.elapsed(time(1100)), Duration::from_secs(200));
+        assert!(game.resume(time(1100)).is_err());
+    }
+
+    #[test]
+    fn test_pause_countdown() {
+        let mut game = Game::default();
+        game.start(time(0), HEAD_START).unwrap();
+        game.pause(time(200)).unwrap();
+        assert_eq!(game.phase, GamePhase::Paused);
+        // the countdown doesn't go on while paused
+        game.update(time(2000), None);
+        assert_eq!(game.phase, GamePhase::Paused);
+
+        // the rest of the head start is left after resuming
+        game.resume(time(2000)).unwrap();
+        assert_eq!(game.phase, GamePhase::Countdown { until: time(2400) });
+        game.update(time(2399), None);
+        assert!(!game.can_move(TeamKind::Detective));
+        game.update(time(2400), None);
+        assert_eq!(game.phase, GamePhase::Running);
+        assert_eq!(game.elapsed(time(2500)), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_finish_paused_countdown() {
+        let mut game = Game::default();
+        game.start(time(0), HEAD_START).unwrap();
+        game.pause(time(200)).unwrap();
+        game.finish(time(300), None).unwrap();
+        assert_eq!(game.phase, GamePhase::Finished);
+        assert_eq!(game.elapsed(time(400)), Duration::ZERO);
+        assert!(game.resume(time(400)).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::point::Point;
+use crate::ws_message::{ErrorCode, PositionUpdate};
+
+/// Fixes less accurate than this (in meters) are discarded.
+const MAX_ACCURACY: f32 = 100.0;
+/// The accuracy (in meters) assumed for fixes that don't report it.
+const DEFAULT_ACCURACY: f32 = 20.0;
+/// Fixes older than this are discarded.
+const MAX_FIX_AGE: Duration = Duration::from_secs(30);
+/// How fast (in m/s) the filtered position loses certainty, i.e. how quickly players change speed or direction.
+const PROCESS_NOISE: f32 = 3.0;
+const METERS_PER_DEGREE: f32 = 111_320.0;
+
+/// A position measured by a device.
+#[derive(Debug, Clone, Copy)]
+pub struct Fix {
+    pub time: DateTime<Utc>,
+    pub position: Point,
+    /// The accuracy in meters.
+    accuracy: f32,
+    /// The velocity in m/s towards north and east.
+    velocity: Option<(f32, f32)>,
+}
+
+impl Fix {
+    /// The fix of a position update received at `now`, if it is recent and accurate enough.
+    pub fn new(update: &PositionUpdate, now: DateTime<Utc>) -> Result<Self, ErrorCode> {
+        // a single invalid coordinate would stay in the filter for good
+        if !(-90.0..=90.0).contains(&update.lat) || !(-180.0..=180.0).contains(&update.long) {
+            return Err(ErrorCode::InvalidPosition);
+        }
+        // the clocks of phones may be ahead
+        let time = update.timestamp.map_or(now, |timestamp| timestamp.min(now));
+        if (now - time).to_std().unwrap_or_default() > MAX_FIX_AGE {
+            return Err(ErrorCode::StalePosition);
+        }
+        let accuracy = update.accuracy.unwrap_or(DEFAULT_ACCURACY);
+        if accuracy.is_nan() || accuracy > MAX_ACCURACY {
+            return Err(ErrorCode::InaccuratePosition);
+        }
+        let velocity = update
+            .speed
+            .zip(update.heading)
+            .filter(|(speed, heading)| speed.is_finite() && heading.is_finite())
+            .map(|(speed, heading)| {
+                let heading = heading.to_radians();
+                (speed * heading.cos(), speed * heading.sin())
+            });
+        Ok(Self {
+            time,
+            position: Point {
+                latitude: update.lat,
+                longitude: update.long,
+            },
+            accuracy: accuracy.max(1.0),
+            velocity,
+        })
+    }
+}
+
+/// Smooths the fixes of a device with a Kalman filter. Each fix is weighted by its accuracy against how uncertain
+/// the filtered position became since the last fix, so the result doesn't depend on how often the device sends.
+#[derive(Debug, Default, Clone)]
+pub struct PositionFilter {
+    last: Option<FilterState>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FilterState {
+    time: DateTime<Utc>,
+    position: Point,
+    /// The variance of the position in m².
+    variance: f32,
+    velocity: Option<(f32, f32)>,
+}
+
+impl PositionFilter {
+    /// Add a fix and return the filtered position, `None` if the fix is older than the last one.
+    pub fn update(&mut self, fix: Fix) -> Option<Point> {
+        let Some(last) = self.last else {
+            self.last = Some(FilterState {
+                time: fix.time,
+                position: fix.position,
+                variance: fix.accuracy * fix.accuracy,
+                velocity: fix.velocity,
+            });
+            return Some(fix.position);
+        };
+        if fix.time < last.time {
+            return None;
+        }
+
+        // predict where the device is now
+        let seconds = (fix.time - last.time).num_milliseconds() as f32 / 1000.;
+        let mut predicted = last.position;
+        if let Some((north, east)) = last.velocity {
+            predicted.latitude += north * seconds / METERS_PER_DEGREE;
+            predicted.longitude += east * seconds / (METERS_PER_DEGREE * predicted.latitude.to_radians().cos());
+        }
+        let variance = last.variance + seconds * PROCESS_NOISE * PROCESS_NOISE;
+
+        let gain = variance / (variance + fix.accuracy * fix.accuracy);
+        let position = predicted.lerp(fix.position, gain);
+        self.last = Some(FilterState {
+            time: fix.time,
+            position,
+            variance: (1. - gain) * variance,
+            velocity: fix.velocity,
+        });
+        Some(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const START: Point = Point {
+        latitude: 49.0,
+        longitude: 8.4,
+    };
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 10, 11, 12, 0, 0).unwrap() + chrono::Duration::seconds(seconds)
+    }
+
+    /// A position `north` meters north of `START`.
+    fn update(north: f32, accuracy: f32) -> PositionUpdate {
+        PositionUpdate {
+            long: START.longitude,
+            lat: START.latitude + north / METERS_PER_DEGREE,
+            accuracy: Some(accuracy),
+            heading: None,
+            speed: None,
+            timestamp: None,
+        }
+    }
+
+    fn north_of_start(position: Point) -> f32 {
+        (position.latitude - START.latitude) * METERS_PER_DEGREE
+    }
+
+    #[test]
+    fn test_inaccurate_fix() {
+        assert!(Fix::new(&update(0., MAX_ACCURACY), time(0)).is_ok());
+        assert!(matches!(
+            Fix::new(&update(0., MAX_ACCURACY + 1.), time(0)),
+            Err(ErrorCode::InaccuratePosition)
+        ));
+        assert!(matches!(
+            Fix::new(&update(0., f32::NAN), time(0)),
+            Err(ErrorCode::InaccuratePosition)
+        ));
+        let fix = Fix::new(
+            &PositionUpdate {
+                accuracy: None,
+                ..update(0., 0.)
+            },
+            time(0),
+        );
+        assert_eq!(fix.unwrap().accuracy, DEFAULT_ACCURACY);
+    }
+
+    #[test]
+    fn test_invalid_fix() {
+        let at = |long, lat| PositionUpdate {
+            long,
+            lat,
+            ..update(0., 10.)
+        };
+        assert!(Fix::new(&at(180., -90.), time(0)).is_ok());
+        for update in [
+            at(f32::NAN, 49.),
+            at(8.4, f32::NAN),
+            at(f32::INFINITY, 49.),
+            at(8.4, f32::NEG_INFINITY),
+            at(180.1, 49.),
+            at(8.4, -90.1),
+        ] {
+            assert!(matches!(Fix::new(&update, time(0)), Err(ErrorCode::InvalidPosition)));
+        }
+    }
+
+    #[test]
+    fn test_stale_fix() {
+        let measured = |seconds| PositionUpdate {
+            timestamp: Some(time(seconds)),
+            ..update(0., 10.)
+        };
+        assert!(Fix::new(&measured(0), time(30)).is_ok());
+        assert!(matches!(
+            Fix::new(&measured(0), time(31)),
+            Err(ErrorCode::StalePosition)
+        ));
+        // the clock of the device is ahead
+        assert_eq!(Fix::new(&measured(10), time(0)).unwrap().time, time(0));
+    }
+
+    #[test]
+    fn test_convergence() {
+        let mut filter = PositionFilter::default();
+        // a device standing still, with fixes scattered 30 m around its position
+        let mut position = START;
+        for i in 0..60 {
+            let north = if i % 2 == 0 { 30. } else { -30. };
+            let fix = Fix::new(&update(north, 30.), time(i)).unwrap();
+            position = filter.update(fix).unwrap();
+        }
+        assert!(north_of_start(position).abs() < 5.);
+
+        // the device walked 100 m, the filter follows within a few fixes
+        let mut distances = Vec::new();
+        for i in 60..70 {
+            let fix = Fix::new(&update(100., 10.), time(i)).unwrap();
+            distances.push(100. - north_of_start(filter.update(fix).unwrap()));
+        }
+        assert!(distances.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(distances[9] < 5.);
+    }
+
+    #[test]
+    fn test_accuracy_weight() {
+        let mut filter = PositionFilter::default();
+        filter.update(Fix::new(&update(0., 5.), time(0)).unwrap());
+        // an inaccurate fix barely moves an accurate position
+        let position = filter.update(Fix::new(&update(50., 100.), time(1)).unwrap()).unwrap();
+        assert!(north_of_start(position) < 5.);
+        // an accurate fix does
+        let position = filter.update(Fix::new(&update(50., 5.), time(2)).unwrap()).unwrap();
+        assert!(north_of_start(position) > 25.);
+    }
+
+    #[test]
+    fn test_out_of_order_fix() {
+        let mut filter = PositionFilter::default();
+        assert!(filter.update(Fix::new(&update(0., 10.), time(10)).unwrap()).is_some());
+        let older = PositionUpdate {
+            timestamp: Some(time(5)),
+            ..update(10., 10.)
+        };
+        assert!(filter.update(Fix::new(&older, time(10)).unwrap()).is_none());
+    }
+}
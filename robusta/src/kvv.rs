@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use futures_util::future::join_all;
 use lazy_static::lazy_static;
 use serde::Serialize;
+use trias::TriasClient;
 
 use std::collections::HashMap;
 use std::sync::OnceLock;
@@ -10,7 +11,9 @@ use std::time::Duration;
 // mod api;
 
 use crate::point::{interpolate_segment, Point};
-use crate::ws_message::Train;
+use crate::reachability::StopGraph;
+use crate::unique_id::UniqueIdGen;
+use crate::ws_message::{Train, TransportMode};
 
 /// The wait time to use when the arrival or departure time is missing.
 const DEFAULT_WAIT_TIME: Duration = Duration::from_secs(30);
@@ -28,6 +31,15 @@ pub struct Stop {
     pub lon: f64,
 }
 
+impl Stop {
+    pub fn position(&self) -> Point {
+        Point {
+            latitude: self.lat as f32,
+            longitude: self.lon as f32,
+        }
+    }
+}
+
 lazy_static! {
     /// The included stops and their IDs.
     static ref STOPS: HashMap<&'static str, &'static str> = HashMap::from([
@@ -111,56 +123,87 @@ fn intermediate_points(start_id: &str, end_id: &str) -> Vec<Point> {
     }
 }
 
-static API_ENDPOINT: OnceLock<String> = OnceLock::new();
-static ACCESS_TOKEN: OnceLock<String> = OnceLock::new();
-
-async fn kvv_stops() -> Vec<Stop> {
-    let access_token = ACCESS_TOKEN.get().unwrap();
-    let api_endpoint = API_ENDPOINT.get().unwrap();
+async fn kvv_stops(client: &TriasClient) -> Vec<Stop> {
     join_all(STOPS.keys().map(|&stop_id| async move {
-        let stops = trias::search_stops(stop_id.to_owned(), access_token.clone(), api_endpoint, 1)
+        let stops = client
+            .search_stops(stop_id.to_owned(), 1)
             .await
-            .unwrap();
+            .map_err(|err| tracing::error!("Failed to look up stop {}: {}", stop_id, err))
+            .ok()?;
 
-        let first_stop = stops.into_iter().next().unwrap();
-        let stop_point = first_stop.stop_point;
+        let first_stop = stops.into_iter().next()?;
+        let stop_point = first_stop.stop_point?;
         let position = first_stop.geo_position;
-        Stop {
+        Some(Stop {
             name: stop_point.stop_point_name.text,
             id: stop_point.stop_point_ref,
             lat: position.latitude,
             lon: position.longitude,
-        }
+        })
     }))
     .await
+    .into_iter()
+    .flatten()
+    .collect()
 }
 
 #[derive(Debug, Clone)]
 pub struct Times {
-    arrival: DateTime<Utc>,
-    departure: DateTime<Utc>,
+    pub arrival: DateTime<Utc>,
+    pub departure: DateTime<Utc>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Journey {
     stops: Vec<(StopRef, Times)>,
+    journey_ref: JourneyRef,
+    operating_day: String,
+    line_ref: String,
     line_name: String,
     destination: String,
+    mode: TransportMode,
 }
 
 impl Journey {
-    fn new(line_name: String, destination: String) -> Self {
+    #[cfg(test)]
+    pub fn with_stops(stops: Vec<(StopRef, Times)>) -> Self {
         Self {
-            stops: Vec::new(),
-            line_name,
-            destination,
+            stops,
+            ..Default::default()
         }
     }
+
+    /// The stops of the journey with their times, in order.
+    pub fn stops(&self) -> impl Iterator<Item = (&str, &Times)> {
+        self.stops.iter().map(|(stop_id, times)| (stop_id.as_str(), times))
+    }
 }
 
 type JourneyRef = String;
 type StopRef = String;
-pub type LineDepartures = HashMap<JourneyRef, Journey>;
+/// The journeys by their train id.
+pub type LineDepartures = HashMap<u32, Journey>;
+
+/// Assigns every journey a train id, which stays the same across departure refreshes.
+#[derive(Debug, Default)]
+pub struct TrainIds {
+    /// The ids by operating day and journey ref, journey refs are only unique on a single day.
+    ids: HashMap<(String, JourneyRef), u32>,
+    id_gen: UniqueIdGen,
+}
+
+impl TrainIds {
+    pub fn get(&mut self, operating_day: &str, journey_ref: &str) -> u32 {
+        let key = (operating_day.to_owned(), journey_ref.to_owned());
+        *self.ids.entry(key).or_insert_with(|| self.id_gen.next())
+    }
+
+    /// Forget the journeys of operating days that are no longer in `departures`.
+    fn retain_days(&mut self, departures: &LineDepartures) {
+        self.ids
+            .retain(|(day, _), _| departures.values().any(|journey| &journey.operating_day == day));
+    }
+}
 
 pub fn get_times(call: &trias::response::CallAtStop) -> Option<Times> {
     let arrival = call
@@ -188,20 +231,26 @@ pub fn get_times(call: &trias::response::CallAtStop) -> Option<Times> {
     }
 }
 
-pub async fn fetch_departures(stops: &[Stop]) -> LineDepartures {
-    let access_token = ACCESS_TOKEN.get().unwrap();
-    let api_endpoint = API_ENDPOINT.get().unwrap();
-
-    let stop_results = join_all(stops.iter().map(|stop| {
-        let name = stop.id.clone();
-        let access_token = access_token.clone();
-        async move {
-            trias::stop_events(name, access_token, 10, api_endpoint)
-                .await
-                .map_err(|err| err.to_string())
-        }
-    }))
-    .await;
+/// The mode of transport of a service. S-Bahn lines are recognized by their name as well, since many of them are
+/// operated with tram-trains.
+pub fn transport_mode(service_section: &trias::response::ServiceSection) -> TransportMode {
+    match service_section.mode.pt_mode.as_str() {
+        "bus" | "trolleyBus" | "coach" => TransportMode::Bus,
+        "rail" | "urbanRail" => TransportMode::SBahn,
+        _ if is_s_bahn_line(&service_section.published_line_name.text) => TransportMode::SBahn,
+        _ => TransportMode::Tram,
+    }
+}
+
+/// Whether a line is named like an S-Bahn line, e.g. `S1` or `S51`, but not `Straßenbahn 2`.
+fn is_s_bahn_line(line_name: &str) -> bool {
+    line_name
+        .strip_prefix('S')
+        .is_some_and(|number| number.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+pub async fn fetch_departures(client: &TriasClient, stops: &[Stop], train_ids: &mut TrainIds) -> LineDepartures {
+    let stop_results = join_all(stops.iter().map(|stop| client.stop_events(stop.id.clone(), 10))).await;
 
     let mut journeys = HashMap::new();
 
@@ -209,6 +258,15 @@ pub async fn fetch_departures(stops: &[Stop]) -> LineDepartures {
         .into_iter()
         .flat_map(|res| match res {
             Ok(x) => x.stop_event_result,
+            // stops without departures and unreachable servers are expected now and then
+            Err(err @ trias::Error::Trias { .. }) => {
+                tracing::debug!("{}", err);
+                Vec::new()
+            }
+            Err(err) if err.is_transient() => {
+                tracing::warn!("{}", err);
+                Vec::new()
+            }
             Err(err) => {
                 tracing::error!("{}", err);
                 Vec::new()
@@ -220,13 +278,19 @@ pub async fn fetch_departures(stops: &[Stop]) -> LineDepartures {
         if service.cancelled {
             continue;
         }
-        let journey_ref = service.journey_ref;
-        let line_name = service.service_section.published_line_name.text;
-        let destination = service.destination_text.text;
-        if journeys.contains_key(&journey_ref) {
+        let id = train_ids.get(&service.operating_day_ref, &service.journey_ref);
+        if journeys.contains_key(&id) {
             continue;
         }
-        let mut journey = Journey::new(line_name, destination);
+        let mut journey = Journey {
+            stops: Vec::new(),
+            mode: transport_mode(&service.service_section),
+            journey_ref: service.journey_ref,
+            operating_day: service.operating_day_ref,
+            line_ref: service.service_section.line_ref,
+            line_name: service.service_section.published_line_name.text,
+            destination: service.destination_text.text,
+        };
         let previous_calls = stop_event.previous_call.into_iter();
         let this_call = stop_event.this_call;
         let next_calls = stop_event.onward_call.into_iter();
@@ -245,8 +309,9 @@ pub async fn fetch_departures(stops: &[Stop]) -> LineDepartures {
             };
             journey.stops.push((stop.id.clone(), times));
         }
-        journeys.insert(journey_ref, journey);
+        journeys.insert(id, journey);
     }
+    train_ids.retain_days(&journeys);
     journeys
 }
 
@@ -280,14 +345,9 @@ pub fn points_on_route(start_stop_id: &str, end_stop_id: &str, stops: &[Stop]) -
     points
 }
 
-pub fn train_position_per_route(
-    time: DateTime<Utc>,
-    journey_ref: &str,
-    departures: &Journey,
-    stops: &[Stop],
-) -> Option<Train> {
+pub fn train_position_per_route(time: DateTime<Utc>, id: u32, departures: &Journey, stops: &[Stop]) -> Option<Train> {
     if departures.stops.is_empty() {
-        tracing::warn!("no departures for journey {}", journey_ref);
+        tracing::warn!("no departures for journey {}", departures.journey_ref);
         return None;
     }
 
@@ -307,12 +367,16 @@ pub fn train_position_per_route(
         let points = points_on_route(stop_id, next_stop_id, stops);
         if let Some(position) = interpolate_segment(&points, progress) {
             return Some(Train {
-                id: 0,
+                id,
                 lat: position.latitude,
                 long: position.longitude,
-                line_id: journey_ref.to_owned(),
+                line_id: departures.line_ref.clone(),
                 line_name,
                 direction: destination,
+                mode: departures.mode,
+                journey_ref: departures.journey_ref.clone(),
+                operating_day: departures.operating_day.clone(),
+                stop_id: next_stop_id.clone(),
             });
         }
     }
@@ -320,38 +384,69 @@ pub fn train_position_per_route(
 }
 
 pub static KVV_STOPS: OnceLock<Vec<Stop>> = OnceLock::new();
+/// The walking transfers between the stops in `KVV_STOPS`.
+pub static KVV_STOP_GRAPH: OnceLock<StopGraph<'static>> = OnceLock::new();
 
-pub async fn init() {
+/// Create the TRIAS client from the environment and load the stops.
+pub async fn init() -> TriasClient {
     let api_endpoint = dotenv::var("TRIAS_API_ENDPOINT").expect("TRIAS_API_ENDPOINT not set");
-    API_ENDPOINT.set(api_endpoint).expect("failed to set API_ENDPOINT");
     let access_token = dotenv::var("TRIAS_ACCESS_TOKEN").expect("TRIAS_ACCESS_TOKEN not set");
-    ACCESS_TOKEN.set(access_token).expect("failed to set ACCESS_TOKEN");
-    let stops = kvv_stops().await;
+    let client = TriasClient::builder(api_endpoint, access_token)
+        .language("de")
+        .build()
+        .expect("failed to create TRIAS client");
+    let stops = kvv_stops(&client).await;
     KVV_STOPS.set(stops).expect("failed to set KVV_STOPS");
+    let stops = KVV_STOPS.get().expect("KVV_STOPS not initialized");
+    KVV_STOP_GRAPH
+        .set(StopGraph::new(stops))
+        .expect("failed to set KVV_STOP_GRAPH");
+    client
 }
 
-pub async fn fetch_departures_for_region() -> LineDepartures {
+pub async fn fetch_departures_for_region(client: &TriasClient, train_ids: &mut TrainIds) -> LineDepartures {
     let stops = KVV_STOPS.get().expect("KVV_STOPS not initialized");
-    fetch_departures(stops).await
+    fetch_departures(client, stops, train_ids).await
 }
 
 pub fn train_positions(departures_per_line: &LineDepartures, render_time: DateTime<Utc>) -> Vec<Train> {
     let stops = KVV_STOPS.get().expect("KVV_STOPS not initialized");
     departures_per_line
         .iter()
-        .flat_map(|(journey_ref, departures)| train_position_per_route(render_time, journey_ref, departures, stops))
+        .flat_map(|(&id, departures)| train_position_per_route(render_time, id, departures, stops))
         .collect()
 }
 
-pub fn nearest_stop(pos: Point) -> &'static Stop {
-    let stops = KVV_STOPS.get().expect("KVV_STOPS not initialized");
-    stops
-        .iter()
-        .min_by_key(|stop| {
-            pos.distance(Point {
-                latitude: stop.lat as f32,
-                longitude: stop.lon as f32,
-            }) as u64
-        })
-        .expect("no stops")
+/// The stop nearest to `pos`, or `None` if no stops are loaded.
+pub fn nearest_stop(pos: Point) -> Option<&'static Stop> {
+    let stops = KVV_STOPS.get()?;
+    stops.iter().min_by_key(|stop| pos.distance(stop.position()) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use trias::mock::MockServer;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let server = MockServer::builder().fixtures().relative_times().start().await.unwrap();
+        let client = TriasClient::new(server.endpoint(), "token".to_owned()).unwrap();
+
+        let stops = kvv_stops(&client).await;
+        assert_eq!(stops.len(), trias::mock::fixtures::STOPS.len());
+        let departures = fetch_departures(&client, &stops, &mut TrainIds::default()).await;
+        let (&id, journey) = departures
+            .iter()
+            .find(|(_, journey)| journey.journey_ref == "kvv:21002:E:H:j23:1234")
+            .unwrap();
+        assert_eq!(journey.stops.len(), 4);
+
+        let train = train_position_per_route(Utc::now(), id, journey, &stops).unwrap();
+        assert_eq!(train.id, id);
+        // the train is about to arrive at the Marktplatz
+        assert_eq!(train.stop_id, "de:08212:1003");
+        assert_eq!(train.mode, TransportMode::Tram);
+    }
 }
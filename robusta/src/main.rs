@@ -9,16 +9,17 @@ use axum::{
     body::{boxed, Body, BoxBody},
     extract::{
         ws::{self, WebSocket, WebSocketUpgrade},
-        State,
+        Path, Query, State,
     },
     http::{Request, Uri},
-    response::Response,
+    response::{IntoResponse, Response},
     routing::{get, get_service, post},
     Json, Router,
 };
 use futures_util::SinkExt;
 use lazy_static::lazy_static;
 use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tower::util::ServiceExt;
 use tower_http::{
@@ -27,31 +28,63 @@ use tower_http::{
 };
 use tracing::{error, info, warn, Level};
 use tracing_appender::rolling::{self, Rotation};
+use trias::TriasClient;
 
+use crate::area::PlayArea;
+use crate::config::GameConfig;
 use crate::kvv::LineDepartures;
+use crate::room::{ClientConnection, Room};
+use crate::trip::{TripPlan, TripQuery};
 use crate::unique_id::UniqueIdGen;
-use crate::ws_message::{ClientMessage, ClientResponse, GameState, Team, TeamKind, TeamState};
+use crate::ws_message::{
+    ClientMessage, ClientRequest, ClientResponse, CreatedTeam, ErrorCode, GameError, GameInfo, GamePhase, GameState,
+    Player, PositionViolation, Team, TeamKind, TeamState,
+};
 
+mod admin;
+mod area;
+mod capture;
+mod config;
+mod delta;
+mod detection;
+mod game;
+mod gps;
 mod kvv;
 mod point;
+mod reachability;
+mod room;
+mod trip;
 mod unique_id;
 mod ws_message;
 
+/// The teams of the single game of older versions, imported into the first game room.
 const TEAMS_FILE: &str = "teams.json";
-
-/// The name used for the Mr. X team.
-const MRX: &str = "Mr. X";
+/// The default config for new game rooms.
+const CONFIG_FILE: &str = "config.json";
+/// The directory containing a persistence file for every game room.
+const ROOMS_DIR: &str = "rooms";
 
 #[derive(Debug)]
 enum InputMessage {
-    Client(ClientMessage, u32),
+    Client(ClientRequest, u32),
     Server(ServerMessage),
+    Admin(admin::AdminRequest),
 }
 
 #[derive(Debug)]
 enum ServerMessage {
     Departures(LineDepartures),
-    ClientDisconnected(u32),
+    /// A client sent a message that could not be parsed.
+    InvalidMessage {
+        id: u32,
+        request_id: Option<u32>,
+        error: String,
+    },
+    /// The socket of a client closed. `reconnects` tells which socket of the session it was.
+    ClientDisconnected {
+        id: u32,
+        reconnects: u32,
+    },
 }
 
 #[derive(Debug)]
@@ -59,64 +92,96 @@ struct Client {
     recv: Receiver<ClientResponse>,
     send: Sender<InputMessage>,
     id: u32,
-}
-
-#[derive(Debug)]
-struct ClientConnection {
-    id: u32,
-    team_id: u32,
-    send: Sender<ClientResponse>,
+    reconnects: u32,
 }
 
 #[derive(Debug)]
 struct AppState {
-    pub teams: Vec<ws_message::TeamState>,
+    pub rooms: Vec<Room>,
     pub game_logic_sender: Sender<InputMessage>,
-    pub connections: Vec<ClientConnection>,
     pub client_id_gen: UniqueIdGen,
-    pub team_id_gen: UniqueIdGen,
+    pub room_id_gen: UniqueIdGen,
+    /// The config of new rooms, if no config is given.
+    pub default_config: GameConfig,
+    /// The client for trip planning, `None` if no train data is fetched.
+    pub trias: Option<TriasClient>,
 }
 
 impl AppState {
-    const fn new(game_logic_sender: Sender<InputMessage>) -> Self {
+    const fn new(game_logic_sender: Sender<InputMessage>, default_config: GameConfig) -> Self {
         Self {
-            teams: Vec::new(),
+            rooms: Vec::new(),
             game_logic_sender,
-            connections: Vec::new(),
             client_id_gen: UniqueIdGen::new(),
-            team_id_gen: UniqueIdGen::new(),
+            room_id_gen: UniqueIdGen::new(),
+            default_config,
+            trias: None,
         }
     }
 
-    fn client(&self, id: u32) -> Option<&ClientConnection> {
-        self.connections.iter().find(|x| x.id == id)
+    fn room_mut(&mut self, id: u32) -> Option<&mut Room> {
+        self.rooms.iter_mut().find(|room| room.id == id)
     }
 
-    fn client_mut(&mut self, id: u32) -> Option<&mut ClientConnection> {
-        self.connections.iter_mut().find(|x| x.id == id)
+    /// The room with the given id, or the oldest room if no id is given.
+    fn room_or_default_mut(&mut self, id: Option<u32>) -> Option<&mut Room> {
+        match id {
+            Some(id) => self.room_mut(id),
+            None => self.rooms.first_mut(),
+        }
     }
 
-    fn team_mut_by_client_id(&mut self, id: u32) -> Option<&mut TeamState> {
-        self.client(id)
-            .map(|x| x.team_id)
-            .and_then(|team_id| self.teams.iter_mut().find(|ts| ts.team.id == team_id))
+    fn room_mut_by_client_id(&mut self, client_id: u32) -> Option<&mut Room> {
+        self.rooms.iter_mut().find(|room| room.client(client_id).is_some())
     }
 }
 
+/// Selects the game room of a request, e.g. `/ws?game=1`. Without it, the oldest room is used.
+#[derive(Deserialize, Debug)]
+struct RoomQuery {
+    game: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SocketQuery {
+    game: Option<u32>,
+    /// The token of a session to resume, the session's room is used then.
+    session: Option<String>,
+}
+
 type SharedState = Arc<tokio::sync::Mutex<AppState>>;
 
-async fn handler(ws: WebSocketUpgrade, State(state): State<SharedState>) -> Response {
+async fn handler(ws: WebSocketUpgrade, Query(query): Query<SocketQuery>, State(state): State<SharedState>) -> Response {
     let (send, rec) = tokio::sync::mpsc::channel(100);
     let client = {
         let mut state = state.lock().await;
-        let id = state.client_id_gen.next();
-        let client_connection = ClientConnection { id, team_id: 0, send };
-        state.connections.push(client_connection);
-        info!("Client {} connected", id);
+        let resumed = query.session.as_deref().and_then(|token| {
+            state.rooms.iter_mut().find_map(|room| {
+                let (id, reconnects) = room.resume_session(token, send.clone())?;
+                room.greet(room.client(id)?);
+                info!("Client {} resumed its session in game {}", id, room.id);
+                Some((id, reconnects))
+            })
+        });
+        let (id, reconnects) = match resumed {
+            Some(resumed) => resumed,
+            None => {
+                let id = state.client_id_gen.next();
+                let Some(room) = state.room_or_default_mut(query.game) else {
+                    return (StatusCode::NOT_FOUND, Json(GameError::NotFound)).into_response();
+                };
+                let client_connection = ClientConnection::new(id, send);
+                room.greet(&client_connection);
+                room.connections.push(client_connection);
+                info!("Client {} connected to game {}", id, room.id);
+                (id, 0)
+            }
+        };
         Client {
             recv: rec,
             send: state.game_logic_sender.clone(),
             id,
+            reconnects,
         }
     };
     ws.on_upgrade(|socket| handle_socket(socket, client))
@@ -128,10 +193,14 @@ async fn handle_socket(socket: WebSocket, mut client: Client) {
     let (mut send, mut recv) = socket.split();
     let client_send = client.send.clone();
     let client_id = client.id;
+    let reconnects = client.reconnects;
 
-    let disconnect = |client_send: Sender<InputMessage>, client_id| async move {
+    let disconnect = move |client_send: Sender<InputMessage>, id| async move {
         client_send
-            .send(InputMessage::Server(ServerMessage::ClientDisconnected(client_id)))
+            .send(InputMessage::Server(ServerMessage::ClientDisconnected {
+                id,
+                reconnects,
+            }))
             .await
             .expect("game logic queue disconnected");
     };
@@ -152,16 +221,20 @@ async fn handle_socket(socket: WebSocket, mut client: Client) {
             };
 
             if let Some(msg) = opt_msg {
-                if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&msg) {
-                    client
-                        .send
-                        .send(InputMessage::Client(client_msg, client.id))
-                        .await
-                        .expect("game logic queue disconnected");
-                } else {
-                    // invalid message
-                    warn!("Received invalid message: {}", msg);
-                }
+                let request = serde_json::from_str::<ClientRequest>(&msg)
+                    .or_else(|_| serde_json::from_str::<ClientMessage>(&msg).map(ClientRequest::from));
+                let input = match request {
+                    Ok(request) => InputMessage::Client(request, client.id),
+                    Err(err) => {
+                        warn!("Received invalid message: {}", msg);
+                        InputMessage::Server(ServerMessage::InvalidMessage {
+                            id: client.id,
+                            request_id: ClientRequest::request_id_of(&msg),
+                            error: err.to_string(),
+                        })
+                    }
+                };
+                client.send.send(input).await.expect("game logic queue disconnected");
             } else {
                 // client disconnected
                 disconnect(client.send, client.id).await;
@@ -179,6 +252,8 @@ async fn handle_socket(socket: WebSocket, mut client: Client) {
             return;
         }
     }
+    // the session was kicked or resumed on another socket
+    let _ = send.close().await;
 }
 
 async fn get_static_file(uri: Uri) -> Result<Response<BoxBody>, (StatusCode, String)> {
@@ -216,36 +291,135 @@ pub async fn file_handler(uri: Uri) -> Result<Response<BoxBody>, (StatusCode, St
 }
 
 async fn create_team(
+    Query(query): Query<RoomQuery>,
     State(state): State<SharedState>,
     Json(team): Json<ws_message::CreateTeam>,
-) -> Result<Json<Team>, (StatusCode, Json<ws_message::CreateTeamError>)> {
+) -> Result<Json<CreatedTeam>, (StatusCode, Json<ws_message::CreateTeamError>)> {
     let mut state = state.lock().await;
+    let Some(room) = state.room_or_default_mut(query.game) else {
+        return Err((StatusCode::NOT_FOUND, Json(ws_message::CreateTeamError::GameNotFound)));
+    };
     let team_name = team.name.trim();
 
     // validation
     let error = |err: ws_message::CreateTeamError| Err((StatusCode::UNPROCESSABLE_ENTITY, Json(err)));
-    if team_name.is_empty() {
+    if room.game.phase != GamePhase::Lobby {
+        return error(ws_message::CreateTeamError::GameAlreadyStarted);
+    } else if team.kind == TeamKind::MrX {
+        return error(ws_message::CreateTeamError::MrXAlreadyExists);
+    } else if team_name.is_empty() {
         return error(ws_message::CreateTeamError::InvalidName);
-    } else if state.teams.iter().any(|ts| ts.team.name == team_name) {
+    } else if room.teams.iter().any(|ts| ts.team.name == team_name) {
         return error(ws_message::CreateTeamError::NameAlreadyExists);
     }
 
     let team = Team {
-        id: state.team_id_gen.next(),
+        id: room.team_id_gen.next(),
         name: team_name.to_owned(),
         color: team.color,
         kind: team.kind,
     };
-    state.teams.push(TeamState {
-        team: team.clone(),
-        ..Default::default()
-    });
-    Ok(Json(team))
+    let join_code = room.add_team(team.clone());
+    Ok(Json(CreatedTeam { team, join_code }))
+}
+
+async fn list_teams(
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<Team>>, (StatusCode, Json<GameError>)> {
+    let mut state = state.lock().await;
+    let room = state
+        .room_or_default_mut(query.game)
+        .ok_or((StatusCode::NOT_FOUND, Json(GameError::NotFound)))?;
+    Ok(Json(room.teams.iter().map(|ts| ts.team.clone()).collect()))
 }
 
-async fn list_teams(State(state): State<SharedState>) -> Json<Vec<Team>> {
+type GameResponse<T> = Result<Json<T>, (StatusCode, Json<GameError>)>;
+
+async fn list_players(
+    Path(team_id): Path<u32>,
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+) -> GameResponse<Vec<Player>> {
+    let mut state = state.lock().await;
+    let room = state
+        .room_or_default_mut(query.game)
+        .ok_or((StatusCode::NOT_FOUND, Json(GameError::NotFound)))?;
+    let team = room
+        .teams
+        .iter()
+        .find(|ts| ts.team.id == team_id)
+        .ok_or((StatusCode::NOT_FOUND, Json(GameError::TeamNotFound)))?;
+    let players = match team.team.kind {
+        // the positions of Mr. X's players would give him away
+        TeamKind::MrX => team.players.iter().map(Player::without_position).collect(),
+        _ => team.players.clone(),
+    };
+    Ok(Json(players))
+}
+
+async fn list_games(State(state): State<SharedState>) -> Json<Vec<GameInfo>> {
     let state = state.lock().await;
-    Json(state.teams.iter().map(|ts| ts.team.clone()).collect())
+    Json(state.rooms.iter().map(Room::info).collect())
+}
+
+async fn get_game(Path(id): Path<u32>, State(state): State<SharedState>) -> GameResponse<GameInfo> {
+    let mut state = state.lock().await;
+    let room = state
+        .room_mut(id)
+        .ok_or((StatusCode::NOT_FOUND, Json(GameError::NotFound)))?;
+    Ok(Json(room.info()))
+}
+
+async fn get_play_area(
+    Query(query): Query<RoomQuery>,
+    State(state): State<SharedState>,
+) -> GameResponse<Option<PlayArea>> {
+    let mut state = state.lock().await;
+    let room = state
+        .room_or_default_mut(query.game)
+        .ok_or((StatusCode::NOT_FOUND, Json(GameError::NotFound)))?;
+    Ok(Json(room.config.play_area.clone()))
+}
+
+/// Selects the session of a request, e.g. `/api/trips?session=...`.
+#[derive(Deserialize, Debug)]
+struct SessionQuery {
+    session: Option<String>,
+}
+
+/// Plan trips with the TRIAS API, e.g. for detectives to get to where Mr. X was seen.
+/// Only players may plan trips, as every request uses up the TRIAS quota of the server.
+async fn plan_trips(
+    State(state): State<SharedState>,
+    Query(session): Query<SessionQuery>,
+    Json(query): Json<TripQuery>,
+) -> GameResponse<Vec<TripPlan>> {
+    let client = {
+        let state = state.lock().await;
+        let is_player = session
+            .session
+            .is_some_and(|token| state.rooms.iter().any(|room| room.has_team_session(&token)));
+        if !is_player {
+            return Err((StatusCode::UNAUTHORIZED, Json(GameError::Unauthorized)));
+        }
+        state.trias.clone()
+    };
+    let client = client.ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(GameError::TripPlanningUnavailable),
+    ))?;
+    let trips = client.trips(query.request()).await.map_err(|err| {
+        warn!("Failed to plan trip: {}", err);
+        let status = match &err {
+            trias::Error::Trias { .. } => StatusCode::NOT_FOUND,
+            err if err.is_transient() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        let message = err.to_string();
+        (status, Json(GameError::TripPlanningFailed { message }))
+    })?;
+    Ok(Json(trips.into_iter().map(TripPlan::from).collect()))
 }
 
 async fn list_stops() -> Json<&'static [kvv::Stop]> {
@@ -259,6 +433,8 @@ async fn list_stops() -> Json<&'static [kvv::Stop]> {
 
 lazy_static! {
     static ref FETCH_TRAINS: bool = dotenv::var("NO_FETCH_TRAINS").is_err();
+    /// Lets every client set the position of its team directly.
+    static ref DEBUG: bool = dotenv::var("DEBUG").is_ok();
 }
 
 #[tokio::main]
@@ -275,16 +451,20 @@ async fn main() {
     let state = load_state(send.clone());
 
     if *FETCH_TRAINS {
-        kvv::init().await;
+        let client = kvv::init().await;
+        state.lock().await.trias = Some(client.clone());
 
         // fetch departures every 60 seconds and send them to the game logic queue
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(60));
+            let mut train_ids = kvv::TrainIds::default();
             loop {
                 interval.tick().await;
-                let departures = kvv::fetch_departures_for_region().await;
+                let departures = kvv::fetch_departures_for_region(&client, &mut train_ids).await;
                 if departures.is_empty() {
+                    // keep the old departures, their trains keep running until their journeys end
                     warn!("Fetched no departures");
+                    continue;
                 }
                 if let Err(err) = send
                     .send(InputMessage::Server(ServerMessage::Departures(departures)))
@@ -302,7 +482,12 @@ async fn main() {
     let api = Router::new()
         .route("/create-team", post(create_team))
         .route("/teams", get(list_teams))
+        .route("/teams/:id/players", get(list_players))
+        .route("/games", get(list_games))
+        .route("/games/:id", get(get_game))
         .route("/stops", get(list_stops))
+        .route("/play-area", get(get_play_area))
+        .route("/trips", post(plan_trips))
         .route("/ping", get(|_: ()| async { "pong" }))
         .with_state(state.clone());
 
@@ -310,6 +495,7 @@ async fn main() {
     let app = Router::new()
         .route("/ws", get(handler))
         .nest("/api", api)
+        .nest("/api/admin", admin::router())
         .nest_service(
             "/",
             get_service(ServeDir::new("../liberica/dist").fallback(ServeFile::new("../liberica/dist/index.html"))),
@@ -317,6 +503,9 @@ async fn main() {
         .layer(CorsLayer::permissive())
         .with_state(state.clone());
 
+    if !admin::is_enabled() {
+        warn!("ADMIN_TOKEN is not set, admin endpoints are disabled");
+    }
     info!("Starting web server");
 
     let port = dotenv::var("PORT").unwrap_or_else(|_| "3000".to_string());
@@ -346,40 +535,66 @@ fn update_bindings() {
 }
 
 fn load_state(send: Sender<InputMessage>) -> SharedState {
-    let mut teams = fs::read_to_string(TEAMS_FILE)
-        .ok()
-        .and_then(|x| serde_json::from_str::<Vec<TeamState>>(&x).ok())
-        .unwrap_or_default();
-
-    let mut state = AppState::new(send.clone());
-    let max_id = teams.iter().map(|ts| ts.team.id).max().unwrap_or(0);
-    state.team_id_gen.set_min(max_id + 1);
-    if !teams.iter().any(|ts| ts.team.kind == TeamKind::MrX) {
-        // no Mr. X present
-        teams.push(TeamState {
-            team: Team {
-                id: state.team_id_gen.next(),
-                name: MRX.to_owned(),
-                color: "#000000".to_owned(),
-                kind: TeamKind::MrX,
-            },
-            ..Default::default()
-        });
+    let mut state = AppState::new(send.clone(), GameConfig::load(CONFIG_FILE));
+
+    fs::create_dir_all(rooms_dir()).expect("failed to create game room directory");
+    let mut rooms: Vec<_> = fs::read_dir(rooms_dir())
+        .expect("failed to read game room directory")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| Room::load(&entry.path()))
+        .collect();
+    rooms.sort_by_key(|room| room.id);
+    let max_id = rooms.iter().map(|room| room.id).max().unwrap_or(0);
+    state.room_id_gen.set_min(max_id + 1);
+
+    if rooms.is_empty() {
+        // start with a single game, taking over the teams of older versions
+        let teams = fs::read_to_string(TEAMS_FILE)
+            .ok()
+            .and_then(|x| serde_json::from_str::<Vec<TeamState>>(&x).ok())
+            .unwrap_or_default();
+        let id = state.room_id_gen.next();
+        rooms.push(Room::with_teams(
+            id,
+            "Mister X".to_owned(),
+            state.default_config.clone(),
+            teams,
+        ));
     }
-    state.teams = teams;
+    state.rooms = rooms;
 
     Arc::new(tokio::sync::Mutex::new(state))
 }
 
-async fn run_game_loop(mut recv: Receiver<InputMessage>, state: SharedState) {
-    let mut departures = HashMap::new();
-    let mut log_file = rolling::Builder::new()
+fn rooms_dir() -> &'static std::path::Path {
+    std::path::Path::new(ROOMS_DIR)
+}
+
+/// A line of the game log: the full game state and the position violations since the last line.
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    #[serde(flatten)]
+    state: &'a GameState,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    violations: &'a [PositionViolation],
+}
+
+fn room_log_file(room_id: u32) -> rolling::RollingFileAppender {
+    rolling::Builder::new()
         .rotation(Rotation::DAILY)
-        .filename_prefix("log")
+        .filename_prefix(format!("log-{}", room_id))
         .filename_suffix("csv")
         .max_log_files(1)
         .build("logs")
-        .expect("failed to initialize rolling file appender");
+        .expect("failed to initialize rolling file appender")
+}
+
+async fn run_game_loop(mut recv: Receiver<InputMessage>, state: SharedState) {
+    let mut departures = HashMap::new();
+    let mut log_files = HashMap::new();
+    // the train positions of the last frame
+    let mut trains = Vec::new();
 
     // the time for a single frame
     let mut interval = tokio::time::interval(Duration::from_millis(500));
@@ -389,106 +604,120 @@ async fn run_game_loop(mut recv: Receiver<InputMessage>, state: SharedState) {
 
         // handle messages
         let mut state = state.lock().await;
+        let time = chrono::Utc::now();
         while let Ok(msg) = recv.try_recv() {
             match msg {
-                InputMessage::Client(msg, id) => {
-                    info!("Got message from client {}: {:?}", id, msg);
-                    match msg {
-                        ClientMessage::Position { long, lat } => {
-                            if let Some(team) = state.team_mut_by_client_id(id) {
-                                team.long = (long + team.long) / 2.;
-                                team.lat = (lat + team.lat) / 2.;
-                            }
-                        }
-                        ClientMessage::SetTeamPosition { long, lat } => {
-                            if let Some(team) = state.team_mut_by_client_id(id) {
-                                team.long = long;
-                                team.lat = lat;
+                InputMessage::Client(ClientRequest { request_id, message }, id) => {
+                    info!("Got message from client {}: {:?}", id, message);
+                    let Some(room) = state.room_mut_by_client_id(id) else {
+                        warn!("Client {} not found", id);
+                        continue;
+                    };
+                    room.seen(id, time);
+                    let result = match message {
+                        ClientMessage::Position(update) => room.update_position(id, &update, time),
+                        ClientMessage::SetTeamPosition { long, lat } => room.set_team_position(id, long, lat),
+                        ClientMessage::Message { channel, text } => room.chat(id, channel, text, time).await,
+                        ClientMessage::JoinTeam {
+                            team_id,
+                            join_code,
+                            player_name,
+                        } => {
+                            let result = room.join_team(id, team_id, &join_code, player_name.as_deref());
+                            if result.is_ok() {
+                                room.send_chat_history(id).await;
                             }
+                            result
                         }
-                        ClientMessage::Message(msg) => {
-                            info!("Got message: {}", msg);
+                        ClientMessage::LeaveTeam => {
+                            room.leave_team(id);
+                            Ok(())
                         }
-                        ClientMessage::JoinTeam { team_id } => {
-                            let Some(client) = state.client_mut(id) else {
-                                warn!("Client {} not found", id);
-                                continue;
-                            };
-                            client.team_id = team_id;
+                        ClientMessage::EmbarkTrain { train_id, black_ticket } => {
+                            room.embark(id, train_id, &trains, black_ticket, time)
                         }
-                        ClientMessage::EmbarkTrain { train_id } => {
-                            if let Some(team) = state.team_mut_by_client_id(id) {
-                                team.on_train = Some(train_id);
-                            }
+                        ClientMessage::DisembarkTrain => room.disembark(id),
+                        ClientMessage::DeltaUpdates { enabled } => {
+                            room.set_delta_updates(id, enabled);
+                            Ok(())
                         }
-                        ClientMessage::DisembarkTrain => {
-                            if let Some(team) = state.team_mut_by_client_id(id) {
-                                team.on_train = None;
-                            }
+                        ClientMessage::Resync => {
+                            room.resync(id);
+                            Ok(())
                         }
+                        ClientMessage::Observe { admin_token } => room.observe(id, &admin_token),
+                        ClientMessage::ClaimCapture => room.claim_capture(id).await,
+                        ClientMessage::ConfirmCapture => room.answer_capture(id, true, time).await,
+                        ClientMessage::RejectCapture => room.answer_capture(id, false, time).await,
+                    };
+                    room.respond(id, request_id, result).await;
+                }
+                InputMessage::Server(ServerMessage::InvalidMessage { id, request_id, error }) => {
+                    if let Some(room) = state.room_mut_by_client_id(id) {
+                        let response = ClientResponse::Error {
+                            request_id,
+                            code: ErrorCode::InvalidMessage,
+                            message: error,
+                        };
+                        room.send_to_client(id, response).await;
                     }
                 }
+                InputMessage::Admin(request) => {
+                    info!(
+                        "Got admin command for game {:?}: {:?}",
+                        request.room_id, request.command
+                    );
+                    let result = match state.room_or_default_mut(request.room_id) {
+                        Some(room) => admin::execute(room, request.command, time).await,
+                        None => Err((StatusCode::NOT_FOUND, GameError::NotFound)),
+                    };
+                    // the requester may be gone already
+                    let _ = request.respond.send(result);
+                }
                 InputMessage::Server(ServerMessage::Departures(deps)) => {
                     departures = deps;
                 }
-                InputMessage::Server(ServerMessage::ClientDisconnected(id)) => {
+                InputMessage::Server(ServerMessage::ClientDisconnected { id, reconnects }) => {
                     info!("Client {} disconnected", id);
-                    state.connections.retain(|x| x.id != id);
+                    for room in state.rooms.iter_mut() {
+                        room.disconnect(id, reconnects);
+                    }
                 }
             }
         }
 
-        // compute train positions
-        let time = chrono::Utc::now();
-        let mut trains = Vec::new();
+        // compute train positions, they are shared by all rooms
         if *FETCH_TRAINS {
             trains = kvv::train_positions(&departures, time);
-            trains.retain(|x| !x.line_id.contains("bus"));
-
-            // update positions for players on trains
-            for team in state.teams.iter_mut() {
-                if let Some(train_id) = &team.on_train {
-                    if let Some(train) = trains.iter().find(|x| &x.line_id == train_id) {
-                        team.long = train.long;
-                        team.lat = train.lat;
-                    }
-                }
-            }
         }
 
-        // log game state
-        let game_state = GameState {
-            teams: state.teams.clone(),
-            trains,
-        };
-        writeln!(
-            log_file,
-            "{}, {}",
-            time.with_timezone(&chrono_tz::Europe::Berlin).to_rfc3339(),
-            serde_json::to_string(&game_state).unwrap()
-        )
-        .unwrap();
-        fs::write(TEAMS_FILE, serde_json::to_string_pretty(&game_state.teams).unwrap()).unwrap();
-
-        // send game state to clients
-        for connection in state.connections.iter_mut() {
-            let game_state = GameState {
-                teams: game_state
-                    .teams
-                    .iter()
-                    .filter(|ts| ts.team.kind == TeamKind::Detective || ts.team.id == connection.team_id)
-                    .cloned()
-                    .collect(),
-                trains: game_state.trains.clone(),
+        for room in state.rooms.iter_mut() {
+            room.drop_expired_sessions();
+            room.update(time, &trains);
+            room.end_journeys(&trains).await;
+            room.check_play_area().await;
+            room.detect_trains(time, &trains).await;
+            room.update_reachable_area(time, &departures).await;
+
+            // log game state
+            let game_state = room.game_state(trains.clone());
+            let log_file = log_files.entry(room.id).or_insert_with(|| room_log_file(room.id));
+            let entry = LogEntry {
+                state: &game_state,
+                violations: room.unlogged_violations(),
             };
-            if let Err(err) = connection
-                .send
-                .send(ClientResponse::GameState(game_state.clone()))
-                .await
-            {
-                error!("failed to send game state to client {}: {}", connection.id, err);
-                continue;
-            }
+            writeln!(
+                log_file,
+                "{}, {}",
+                time.with_timezone(&chrono_tz::Europe::Berlin).to_rfc3339(),
+                serde_json::to_string(&entry).unwrap()
+            )
+            .unwrap();
+            room.save_if_changed(rooms_dir());
+
+            // send game state to clients
+            room.announce_phase().await;
+            room.send_game_state(time, &game_state).await;
         }
     }
 }
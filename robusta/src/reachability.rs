@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::kvv::{LineDepartures, Stop};
+use crate::point::Point;
+
+/// The walking speed of Mr. X in m/s.
+const WALKING_SPEED: f32 = 1.4;
+/// How far apart two stops may be to walk from one to the other between trains, in meters.
+const MAX_TRANSFER_DISTANCE: f32 = 500.0;
+/// How far Mr. X may have walked away from a stop, in meters. Larger circles would cover the whole map.
+const MAX_WALKING_RADIUS: f32 = 1000.0;
+
+/// A stop Mr. X could have reached since his last reveal.
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReachableStop {
+    pub stop_id: String,
+    pub long: f32,
+    pub lat: f32,
+    /// The earliest time Mr. X could have been at the stop.
+    pub arrival: DateTime<Utc>,
+    /// How far Mr. X could have walked from the stop since then, in meters.
+    pub radius: f32,
+    /// How early Mr. X could have been at the stop, from 1 right at the reveal to 0 just now.
+    pub weight: f32,
+}
+
+/// The stops with the stops within walking distance of each. Finding those is quadratic in the number of stops, so
+/// the graph is built once when the stops are loaded.
+#[derive(Debug)]
+pub struct StopGraph<'a> {
+    stops: &'a [Stop],
+    indices: HashMap<&'a str, usize>,
+    positions: Vec<Point>,
+    /// The stops within walking distance of each stop, with their distance.
+    transfers: Vec<Vec<(usize, f32)>>,
+}
+
+impl<'a> StopGraph<'a> {
+    pub fn new(stops: &'a [Stop]) -> Self {
+        let indices = stops
+            .iter()
+            .enumerate()
+            .map(|(i, stop)| (stop.id.as_str(), i))
+            .collect();
+        let positions: Vec<Point> = stops.iter().map(Stop::position).collect();
+        let transfers = transfers(&positions);
+        Self {
+            stops,
+            indices,
+            positions,
+            transfers,
+        }
+    }
+}
+
+/// The stops Mr. X could have reached by `now`, walking or by train, when he was revealed at `start` at `since`.
+///
+/// This is an earliest arrival search over the connections between consecutive stops of the journeys, in order of
+/// their departure. Journeys that ended before the last departure refresh are no longer known and not used.
+pub fn reachable_stops(
+    start: Point,
+    on_train: Option<u32>,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+    departures: &LineDepartures,
+    graph: &StopGraph,
+) -> Vec<ReachableStop> {
+    if now <= since {
+        return Vec::new();
+    }
+    let StopGraph {
+        stops,
+        indices,
+        positions,
+        transfers,
+    } = graph;
+
+    // a connection is a train going from one stop to the next: (departure, arrival, train id, from, to)
+    let mut connections: Vec<_> = departures
+        .iter()
+        .flat_map(|(&train_id, journey)| {
+            let stops: Vec<_> = journey.stops().collect();
+            stops
+                .windows(2)
+                .filter_map(|pair| {
+                    let [(from, departure), (to, arrival)] = pair else {
+                        return None;
+                    };
+                    Some((
+                        departure.departure,
+                        arrival.arrival,
+                        train_id,
+                        *indices.get(from)?,
+                        *indices.get(to)?,
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+        // the train Mr. X was on when he was revealed left its last stop before
+        .filter(|&(departure, arrival, train_id, ..)| {
+            (departure >= since || on_train == Some(train_id)) && arrival >= since && arrival <= now
+        })
+        .collect();
+    connections.sort_unstable_by_key(|&(departure, ..)| departure);
+
+    // the trains Mr. X could be on
+    let mut boarded = HashSet::new();
+    let mut earliest = vec![DateTime::<Utc>::MAX_UTC; stops.len()];
+    match on_train.filter(|train_id| departures.contains_key(train_id)) {
+        Some(train_id) => {
+            boarded.insert(train_id);
+        }
+        None => {
+            for (i, &position) in positions.iter().enumerate() {
+                earliest[i] = since + walking_time(start.distance(position));
+            }
+        }
+    }
+
+    for (departure, arrival, train_id, from, to) in connections {
+        if !boarded.contains(&train_id) && earliest[from] > departure {
+            continue;
+        }
+        boarded.insert(train_id);
+        if arrival >= earliest[to] {
+            continue;
+        }
+        earliest[to] = arrival;
+        for &(other, distance) in &transfers[to] {
+            earliest[other] = earliest[other].min(arrival + walking_time(distance));
+        }
+    }
+
+    let duration = (now - since).num_seconds() as f32;
+    stops
+        .iter()
+        .zip(positions)
+        .zip(earliest)
+        .filter(|&(_, arrival)| arrival <= now)
+        .map(|((stop, position), arrival)| {
+            let left = (now - arrival).num_seconds() as f32;
+            ReachableStop {
+                stop_id: stop.id.clone(),
+                long: position.longitude,
+                lat: position.latitude,
+                arrival,
+                radius: (left * WALKING_SPEED).min(MAX_WALKING_RADIUS),
+                weight: left / duration,
+            }
+        })
+        .collect()
+}
+
+fn walking_time(distance: f32) -> chrono::Duration {
+    chrono::Duration::milliseconds((distance / WALKING_SPEED * 1000.) as i64)
+}
+
+/// The stops within walking distance of each stop, with their distance.
+fn transfers(positions: &[Point]) -> Vec<Vec<(usize, f32)>> {
+    positions
+        .iter()
+        .enumerate()
+        .map(|(i, &position)| {
+            positions
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(j, &other)| (j, position.distance(other)))
+                .filter(|&(_, distance)| distance <= MAX_TRANSFER_DISTANCE)
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::kvv::{Journey, Times};
+
+    fn stop(id: &str, lat: f64) -> Stop {
+        Stop {
+            name: id.to_owned(),
+            id: id.to_owned(),
+            lat,
+            lon: 8.4,
+        }
+    }
+
+    /// `b` is 1.4 km north of `a`, `c` 170 m north of `b` and `d` 11 km away.
+    fn stops() -> Vec<Stop> {
+        vec![stop("a", 49.0), stop("b", 49.013), stop("c", 49.0145), stop("d", 49.1)]
+    }
+
+    fn time(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 10, 11, 12, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    /// A train from `a` to `b`, departing and arriving at the given minutes.
+    fn departures(departure: i64, arrival: i64) -> LineDepartures {
+        let times = |minutes| Times {
+            arrival: time(minutes),
+            departure: time(minutes),
+        };
+        let journey = Journey::with_stops(vec![
+            ("a".to_owned(), times(departure)),
+            ("b".to_owned(), times(arrival)),
+        ]);
+        HashMap::from([(1, journey)])
+    }
+
+    fn ids(stops: &[ReachableStop]) -> Vec<&str> {
+        stops.iter().map(|stop| stop.stop_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_transfers() {
+        let stops = stops();
+        let graph = StopGraph::new(&stops);
+        assert!(graph.transfers[0].is_empty());
+        assert_eq!(graph.transfers[1].len(), 1);
+        assert_eq!(graph.transfers[1][0].0, 2);
+        assert_eq!(graph.transfers[2][0].0, 1);
+    }
+
+    #[test]
+    fn test_walking() {
+        let stops = stops();
+        let graph = StopGraph::new(&stops);
+        let start = stops[0].position();
+        let reachable = reachable_stops(start, None, time(0), time(5), &HashMap::new(), &graph);
+        assert_eq!(ids(&reachable), ["a"]);
+        assert_eq!(reachable[0].arrival, time(0));
+        assert_eq!(reachable[0].weight, 1.0);
+        assert_eq!(reachable[0].radius, 5. * 60. * WALKING_SPEED);
+
+        // walking 1.4 km takes about 17 minutes
+        let reachable = reachable_stops(start, None, time(0), time(20), &HashMap::new(), &graph);
+        assert_eq!(ids(&reachable), ["a", "b", "c"]);
+        assert_eq!(reachable[0].radius, MAX_WALKING_RADIUS);
+
+        assert!(reachable_stops(start, None, time(0), time(0), &HashMap::new(), &graph).is_empty());
+    }
+
+    #[test]
+    fn test_train() {
+        let stops = stops();
+        let graph = StopGraph::new(&stops);
+        let start = stops[0].position();
+        let reachable = reachable_stops(start, None, time(0), time(10), &departures(1, 3), &graph);
+        assert_eq!(ids(&reachable), ["a", "b", "c"]);
+        assert_eq!(reachable[1].arrival, time(3));
+        // the transfer from `b` to `c` takes two minutes
+        assert!(reachable[2].arrival > time(4) && reachable[2].arrival < time(6));
+
+        // not arrived yet
+        let reachable = reachable_stops(start, None, time(0), time(2), &departures(1, 3), &graph);
+        assert_eq!(ids(&reachable), ["a"]);
+        // departed before the reveal
+        let reachable = reachable_stops(start, None, time(0), time(10), &departures(-1, 3), &graph);
+        assert_eq!(ids(&reachable), ["a"]);
+    }
+
+    #[test]
+    fn test_on_train() {
+        let stops = stops();
+        let graph = StopGraph::new(&stops);
+        let start = stops[0].position().lerp(stops[1].position(), 0.5);
+        let reachable = reachable_stops(start, Some(1), time(0), time(10), &departures(-1, 3), &graph);
+        assert_eq!(ids(&reachable), ["b", "c"]);
+        assert_eq!(reachable[0].arrival, time(3));
+
+        // the train is no longer known, Mr. X could have walked from where he was revealed
+        let reachable = reachable_stops(start, Some(2), time(0), time(10), &departures(-1, 3), &graph);
+        assert_eq!(ids(&reachable), ["a", "b"]);
+    }
+}
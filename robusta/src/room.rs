@@ -0,0 +1,1534 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use tracing::{error, info, warn};
+
+use crate::admin;
+use crate::capture;
+use crate::config::{GameConfig, TrainDetection};
+use crate::delta::DeltaEncoder;
+use crate::detection::{Detection, TrainDetector, TrainTracks};
+use crate::game::{Game, InvalidTransition};
+use crate::gps::{Fix, PositionFilter};
+use crate::kvv;
+use crate::point::Point;
+use crate::reachability;
+use crate::unique_id::UniqueIdGen;
+use crate::ws_message::{
+    ChatChannel, ChatMessage, ClientResponse, ConnectionInfo, DisembarkReason, ErrorCode, GameError, GameInfo,
+    GamePhase, GameResult, GameState, MrXReveal, Player, PositionUpdate, PositionViolation, Team, TeamJoinCode,
+    TeamKind, TeamState, Ticket, Train, UpdateTeam, UsedTicket,
+};
+
+/// The name used for the Mr. X team.
+const MRX: &str = "Mr. X";
+
+/// The characters of join codes, without characters that are easily confused like `0` and `O`.
+const JOIN_CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const JOIN_CODE_LEN: usize = 6;
+
+/// Only positions of players that are at most this old are used for the position of their team.
+const PLAYER_POSITION_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// The number of chat messages a client gets when it (re)connects.
+const CHAT_HISTORY_LEN: usize = 50;
+
+/// How often the detectives are told where Mr. X could be.
+const REACHABLE_AREA_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_CHAT_MESSAGE_LEN: usize = 500;
+
+/// How often a changed room is saved at most.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the session of a disconnected client can be resumed.
+const SESSION_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60);
+
+/// How far (in meters) positions may jump because of inaccurate GPS, on top of the maximum speed.
+const GPS_TOLERANCE: f32 = 50.0;
+
+/// How close (in meters) a team has to be to a train, or to the stop the train serves, to embark it.
+const EMBARK_RADIUS: f32 = 100.0;
+
+#[derive(Debug)]
+pub struct ClientConnection {
+    pub id: u32,
+    pub team_id: u32,
+    /// The player of the client, `None` for anonymous clients.
+    pub player_id: Option<u32>,
+    /// Whether the client sees the whole game state.
+    pub observer: bool,
+    pub updates: DeltaEncoder,
+    pub send: Sender<ClientResponse>,
+    /// The secret token to resume the session after a reconnect.
+    pub session_token: String,
+    /// How often the session was resumed, to ignore disconnects of replaced sockets.
+    pub reconnects: u32,
+    /// When the client disconnected, `None` while it is connected.
+    pub disconnected_since: Option<Instant>,
+    /// The last plausible filtered position of the client and when it was measured.
+    last_position: Option<(DateTime<Utc>, Point)>,
+    position_filter: PositionFilter,
+    /// Whether the last position the client sent was implausible.
+    implausible: bool,
+}
+
+impl ClientConnection {
+    pub fn new(id: u32, send: Sender<ClientResponse>) -> Self {
+        let session_token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        Self {
+            id,
+            team_id: 0,
+            player_id: None,
+            observer: false,
+            updates: DeltaEncoder::default(),
+            send,
+            session_token,
+            reconnects: 0,
+            disconnected_since: None,
+            last_position: None,
+            position_filter: PositionFilter::default(),
+            implausible: false,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.disconnected_since.is_none()
+    }
+}
+
+/// A game room. Every room has its own teams, connections, config and game.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Room {
+    pub id: u32,
+    pub name: String,
+    pub config: GameConfig,
+    pub teams: Vec<TeamState>,
+    pub game: Game,
+    /// The secret codes needed to join the teams, by team id.
+    #[serde(default)]
+    join_codes: HashMap<u32, String>,
+    #[serde(default)]
+    pub chat: Vec<ChatMessage>,
+    #[serde(skip)]
+    pub connections: Vec<ClientConnection>,
+    #[serde(skip)]
+    pub team_id_gen: UniqueIdGen,
+    #[serde(skip)]
+    player_id_gen: UniqueIdGen,
+    /// The phase the clients were last told about.
+    #[serde(skip)]
+    announced_phase: GamePhase,
+    /// The recent game states, to show observers the state of `observer_delay` ago.
+    #[serde(skip)]
+    observer_feed: VecDeque<(DateTime<Utc>, GameState)>,
+    /// The recent positions of the trains, to detect teams on them.
+    #[serde(skip)]
+    train_tracks: TrainTracks,
+    /// The train detection of each team, by team id.
+    #[serde(skip)]
+    train_detectors: HashMap<u32, TrainDetector>,
+    /// How many of the game's violations were written to the game log.
+    #[serde(skip)]
+    logged_violations: usize,
+    /// The teams outside of the play area.
+    #[serde(skip)]
+    out_of_bounds: HashSet<u32>,
+    /// When the detectives were last told where Mr. X could be.
+    #[serde(skip)]
+    reachable_area_sent: Option<DateTime<Utc>>,
+    /// When the room was last saved and the hash of the saved content.
+    #[serde(skip)]
+    saved: Option<(Instant, u64)>,
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Room {
+    pub fn new(id: u32, name: String, config: GameConfig) -> Self {
+        Self::with_teams(id, name, config, Vec::new())
+    }
+
+    /// Create a room with existing teams, adding a Mr. X team if there is none.
+    pub fn with_teams(id: u32, name: String, config: GameConfig, teams: Vec<TeamState>) -> Self {
+        let mut room = Self {
+            id,
+            name,
+            config,
+            teams,
+            game: Game::default(),
+            join_codes: HashMap::new(),
+            chat: Vec::new(),
+            connections: Vec::new(),
+            team_id_gen: UniqueIdGen::new(),
+            player_id_gen: UniqueIdGen::new(),
+            announced_phase: GamePhase::default(),
+            observer_feed: VecDeque::new(),
+            train_tracks: TrainTracks::default(),
+            train_detectors: HashMap::new(),
+            logged_violations: 0,
+            out_of_bounds: HashSet::new(),
+            reachable_area_sent: None,
+            saved: None,
+        };
+        room.init();
+        room
+    }
+
+    /// Load a room from its persistence file.
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut room = serde_json::from_str::<Self>(&content)
+            .map_err(|err| warn!("Invalid game file {}: {}", path.display(), err))
+            .ok()?;
+        room.init();
+        Some(room)
+    }
+
+    fn init(&mut self) {
+        let max_id = self.teams.iter().map(|ts| ts.team.id).max().unwrap_or(0);
+        self.team_id_gen.set_min(max_id + 1);
+        let max_player_id = self.players().map(|player| player.id).max().unwrap_or(0);
+        self.player_id_gen.set_min(max_player_id + 1);
+        self.logged_violations = self.game.violations.len();
+        // rooms saved by older versions kept the whole chat
+        self.trim_chat();
+        // train ids are assigned anew after a restart
+        for team in &mut self.teams {
+            team.on_train = None;
+        }
+        if !self.teams.iter().any(|ts| ts.team.kind == TeamKind::MrX) {
+            // no Mr. X present
+            self.teams.push(TeamState {
+                team: Team {
+                    id: self.team_id_gen.next(),
+                    name: MRX.to_owned(),
+                    color: "#000000".to_owned(),
+                    kind: TeamKind::MrX,
+                },
+                ..Default::default()
+            });
+        }
+        for team_id in self.teams.iter().map(|ts| ts.team.id).collect::<Vec<_>>() {
+            if !self.join_codes.contains_key(&team_id) {
+                self.issue_join_code(team_id);
+            }
+        }
+        self.announced_phase = self.game.phase;
+    }
+
+    /// Add a new team to the room and return its join code.
+    pub fn add_team(&mut self, team: Team) -> String {
+        let team_id = team.id;
+        self.teams.push(TeamState {
+            team,
+            ..Default::default()
+        });
+        self.issue_join_code(team_id)
+    }
+
+    /// Generate a new join code for the team, replacing the old one.
+    pub fn issue_join_code(&mut self, team_id: u32) -> String {
+        let mut rng = rand::thread_rng();
+        let code: String = (0..JOIN_CODE_LEN)
+            .map(|_| JOIN_CODE_CHARS[rng.gen_range(0..JOIN_CODE_CHARS.len())] as char)
+            .collect();
+        self.join_codes.insert(team_id, code.clone());
+        code
+    }
+
+    pub fn mr_x_join_code(&self) -> Option<&str> {
+        self.join_code(self.mr_x()?.team.id)
+    }
+
+    pub fn join_code(&self, team_id: u32) -> Option<&str> {
+        self.join_codes.get(&team_id).map(String::as_str)
+    }
+
+    /// Let a client join a team if it knows the team's join code.
+    pub fn join_team(
+        &mut self,
+        client_id: u32,
+        team_id: u32,
+        join_code: &str,
+        player_name: Option<&str>,
+    ) -> Result<(), ErrorCode> {
+        let Some(code) = self.join_code(team_id) else {
+            return Err(ErrorCode::TeamNotFound);
+        };
+        // join codes are case insensitive, they are typed in on phones
+        if !code.eq_ignore_ascii_case(join_code.trim()) {
+            return Err(ErrorCode::WrongJoinCode);
+        }
+        let player_id = player_name
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| self.player_id(team_id, name));
+        if let Some(client) = self.client_mut(client_id) {
+            client.team_id = team_id;
+            client.player_id = player_id;
+        }
+        Ok(())
+    }
+
+    /// Let the client see the whole game state if it knows the admin token.
+    pub fn observe(&mut self, client_id: u32, admin_token: &str) -> Result<(), ErrorCode> {
+        if !admin::is_admin_token(admin_token) {
+            return Err(ErrorCode::NotAdmin);
+        }
+        if let Some(client) = self.client_mut(client_id) {
+            client.observer = true;
+            info!("Client {} observes game {}", client_id, self.id);
+        }
+        Ok(())
+    }
+
+    pub fn set_delta_updates(&mut self, client_id: u32, enabled: bool) {
+        if let Some(client) = self.client_mut(client_id) {
+            client.updates.enabled = enabled;
+            client.updates.resync();
+        }
+    }
+
+    pub fn resync(&mut self, client_id: u32) {
+        if let Some(client) = self.client_mut(client_id) {
+            client.updates.resync();
+        }
+    }
+
+    /// Leave the team of the client.
+    pub fn leave_team(&mut self, client_id: u32) {
+        if let Some(client) = self.client_mut(client_id) {
+            client.team_id = 0;
+            client.player_id = None;
+        }
+    }
+
+    /// The id of the player of the team with the name, adding the player if there is none.
+    fn player_id(&mut self, team_id: u32, name: &str) -> u32 {
+        let Some(team) = self.teams.iter_mut().find(|ts| ts.team.id == team_id) else {
+            return 0;
+        };
+        if let Some(player) = team
+            .players
+            .iter()
+            .find(|player| player.name.eq_ignore_ascii_case(name))
+        {
+            return player.id;
+        }
+        let player = Player {
+            id: self.player_id_gen.next(),
+            name: name.to_owned(),
+            team_id,
+            ..Default::default()
+        };
+        info!("Player {} ({}) joined team {}", player.id, player.name, team_id);
+        team.players.push(player.clone());
+        player.id
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &Player> {
+        self.teams.iter().flat_map(|ts| ts.players.iter())
+    }
+
+    fn player_mut_by_client_id(&mut self, client_id: u32) -> Option<&mut Player> {
+        let client = self.client(client_id)?;
+        let (team_id, player_id) = (client.team_id, client.player_id?);
+        self.teams
+            .iter_mut()
+            .find(|ts| ts.team.id == team_id)?
+            .players
+            .iter_mut()
+            .find(|player| player.id == player_id)
+    }
+
+    /// Remember that the client's player was active.
+    pub fn seen(&mut self, client_id: u32, time: DateTime<Utc>) {
+        if let Some(player) = self.player_mut_by_client_id(client_id) {
+            player.last_seen = Some(time);
+        }
+    }
+
+    /// Handle a position reported by a client. The positions of each client are smoothed, the position of the
+    /// team is the center of its players. Anonymous clients move the team directly.
+    pub fn update_position(
+        &mut self,
+        client_id: u32,
+        update: &PositionUpdate,
+        time: DateTime<Utc>,
+    ) -> Result<(), ErrorCode> {
+        let fix = Fix::new(update, time)?;
+        let player_id = self.client(client_id).and_then(|client| client.player_id);
+        let team = self.movable_team_mut_by_client_id(client_id)?;
+        let (team_id, on_train) = (team.team.id, team.on_train.is_some());
+        let Some(client) = self.client(client_id) else {
+            return Ok(());
+        };
+        // the fix is only added to the filter if the filtered position is plausible
+        let mut position_filter = client.position_filter.clone();
+        let position = position_filter.update(fix).ok_or(ErrorCode::StalePosition)?;
+        // teams on trains are fast, but follow the train anyway
+        if !on_train {
+            self.check_position(client_id, team_id, fix.time, position)?;
+        }
+        if let Some(client) = self.client_mut(client_id) {
+            client.position_filter = position_filter;
+            client.last_position = Some((fix.time, position));
+        }
+
+        let team = self.movable_team_mut_by_client_id(client_id)?;
+        let Some(player) = player_id.and_then(|id| team.players.iter_mut().find(|player| player.id == id)) else {
+            team.long = position.longitude;
+            team.lat = position.latitude;
+            self.track_team(team_id, time);
+            return Ok(());
+        };
+        player.long = position.longitude;
+        player.lat = position.latitude;
+        player.located_at = Some(fix.time);
+
+        let located: Vec<_> = team
+            .players
+            .iter()
+            .filter(|player| {
+                player
+                    .located_at
+                    .is_some_and(|at| (time - at).to_std().unwrap_or_default() <= PLAYER_POSITION_MAX_AGE)
+            })
+            .filter_map(Player::position)
+            .collect();
+        if !located.is_empty() {
+            team.long = located.iter().map(|pos| pos.longitude).sum::<f32>() / located.len() as f32;
+            team.lat = located.iter().map(|pos| pos.latitude).sum::<f32>() / located.len() as f32;
+        }
+        self.track_team(team_id, time);
+        Ok(())
+    }
+
+    /// Check that reaching the filtered `position` didn't need an impossible speed, recording a violation otherwise.
+    /// Returns an error if implausible positions are rejected.
+    fn check_position(
+        &mut self,
+        client_id: u32,
+        team_id: u32,
+        time: DateTime<Utc>,
+        position: Point,
+    ) -> Result<(), ErrorCode> {
+        let Some(max_speed_kmh) = self.config.position_check.max_speed_kmh else {
+            return Ok(());
+        };
+        let reject = self.config.position_check.reject;
+        let Some(client) = self.client_mut(client_id) else {
+            return Ok(());
+        };
+        let Some((last_time, last_position)) = client.last_position else {
+            return Ok(());
+        };
+        let seconds = (time - last_time).num_milliseconds().max(1) as f32 / 1000.;
+        let speed_kmh = (last_position.distance(position) - GPS_TOLERANCE).max(0.) / seconds * 3.6;
+        if speed_kmh <= max_speed_kmh {
+            client.implausible = false;
+            return Ok(());
+        }
+
+        // only record the first of consecutive implausible positions, the following ones are likely just as far away
+        let first = !client.implausible;
+        client.implausible = true;
+        let player_id = client.player_id;
+        if first {
+            warn!(
+                "Client {} of team {} moved with {:.0} km/h in game {}",
+                client_id, team_id, speed_kmh, self.id
+            );
+            self.game.violations.push(PositionViolation {
+                time,
+                team_id,
+                player_id,
+                long: position.longitude,
+                lat: position.latitude,
+                speed_kmh,
+                rejected: reject,
+            });
+        }
+        if reject {
+            return Err(ErrorCode::ImplausiblePosition);
+        }
+        Ok(())
+    }
+
+    /// Record the measured position of the team for the train detection.
+    fn track_team(&mut self, team_id: u32, time: DateTime<Utc>) {
+        if let Some(team) = self.teams.iter().find(|ts| ts.team.id == team_id) {
+            self.train_detectors
+                .entry(team_id)
+                .or_default()
+                .record(time, team.position());
+        }
+    }
+
+    /// Move the client's team directly, only admins may do this outside of debug mode.
+    pub fn set_team_position(&mut self, client_id: u32, long: f32, lat: f32) -> Result<(), ErrorCode> {
+        if !*crate::DEBUG && !self.client(client_id).is_some_and(|client| client.observer) {
+            return Err(ErrorCode::NotAdmin);
+        }
+        let team = self.movable_team_mut_by_client_id(client_id)?;
+        team.long = long;
+        team.lat = lat;
+        Ok(())
+    }
+
+    pub fn disembark(&mut self, client_id: u32) -> Result<(), ErrorCode> {
+        self.movable_team_mut_by_client_id(client_id)?.on_train = None;
+        Ok(())
+    }
+
+    /// Rotate the join code of a team. Clients of the team have to join again with the new code.
+    pub fn rotate_join_code(&mut self, team_id: u32) -> String {
+        for connection in self.connections.iter_mut().filter(|x| x.team_id == team_id) {
+            connection.team_id = 0;
+            connection.player_id = None;
+        }
+        self.issue_join_code(team_id)
+    }
+
+    /// The path of the persistence file of the room in `dir`.
+    pub fn path(dir: &Path, id: u32) -> std::path::PathBuf {
+        dir.join(format!("{}.json", id))
+    }
+
+    pub fn save(&mut self, dir: &Path) {
+        let content = serde_json::to_string_pretty(self).unwrap();
+        fs::write(Self::path(dir, self.id), &content).unwrap();
+        self.saved = Some((Instant::now(), content_hash(&content)));
+    }
+
+    /// Save the room if it changed since it was last saved, at most every `SAVE_INTERVAL`.
+    pub fn save_if_changed(&mut self, dir: &Path) {
+        if self.saved.is_some_and(|(at, _)| at.elapsed() < SAVE_INTERVAL) {
+            return;
+        }
+        let content = serde_json::to_string_pretty(self).unwrap();
+        let hash = content_hash(&content);
+        if self.saved.is_none_or(|(_, saved)| saved != hash) {
+            fs::write(Self::path(dir, self.id), &content).unwrap();
+        }
+        self.saved = Some((Instant::now(), hash));
+    }
+
+    pub fn info(&self) -> GameInfo {
+        GameInfo {
+            id: self.id,
+            name: self.name.clone(),
+            phase: self.game.phase,
+            result: self.game.result,
+            config: self.config.clone(),
+        }
+    }
+
+    pub fn client(&self, id: u32) -> Option<&ClientConnection> {
+        self.connections.iter().find(|x| x.id == id)
+    }
+
+    pub fn client_mut(&mut self, id: u32) -> Option<&mut ClientConnection> {
+        self.connections.iter_mut().find(|x| x.id == id)
+    }
+
+    fn team_by_client_id(&self, id: u32) -> Result<&TeamState, ErrorCode> {
+        let team_id = self.client(id).map_or(0, |client| client.team_id);
+        self.teams
+            .iter()
+            .find(|ts| ts.team.id == team_id)
+            .ok_or(ErrorCode::NotInTeam)
+    }
+
+    /// The team of a client, if the current game phase allows the team to move.
+    fn movable_team_mut_by_client_id(&mut self, id: u32) -> Result<&mut TeamState, ErrorCode> {
+        let team_id = self.client(id).map_or(0, |client| client.team_id);
+        let team = self
+            .teams
+            .iter_mut()
+            .find(|ts| ts.team.id == team_id)
+            .ok_or(ErrorCode::NotInTeam)?;
+        if !self.game.can_move(team.team.kind) {
+            return Err(ErrorCode::CannotMove { phase: self.game.phase });
+        }
+        Ok(team)
+    }
+
+    pub fn mr_x(&self) -> Option<&TeamState> {
+        self.teams.iter().find(|ts| ts.team.kind == TeamKind::MrX)
+    }
+
+    /// Resume the session with the token, sending further responses to `send`.
+    /// Returns the client and the number of reconnects.
+    pub fn resume_session(&mut self, session_token: &str, send: Sender<ClientResponse>) -> Option<(u32, u32)> {
+        let connection = self.connections.iter_mut().find(|x| x.session_token == session_token)?;
+        // dropping the old sender closes the replaced socket
+        connection.send = send;
+        connection.reconnects += 1;
+        connection.disconnected_since = None;
+        // the client may have missed updates
+        connection.updates.resync();
+        Some((connection.id, connection.reconnects))
+    }
+
+    /// Whether the session belongs to a client that joined a team of this room.
+    pub fn has_team_session(&self, session_token: &str) -> bool {
+        self.connections
+            .iter()
+            .find(|x| x.session_token == session_token)
+            .is_some_and(|x| self.teams.iter().any(|ts| ts.team.id == x.team_id))
+    }
+
+    /// Keep the session of a disconnected client for the grace period.
+    pub fn disconnect(&mut self, client_id: u32, reconnects: u32) {
+        if let Some(connection) = self
+            .client_mut(client_id)
+            .filter(|x| x.reconnects == reconnects && x.is_connected())
+        {
+            connection.disconnected_since = Some(Instant::now());
+        }
+    }
+
+    /// Remove the sessions of clients that didn't reconnect within the grace period.
+    pub fn drop_expired_sessions(&mut self) {
+        self.connections.retain(|x| {
+            let expired = x
+                .disconnected_since
+                .is_some_and(|since| since.elapsed() > SESSION_GRACE_PERIOD);
+            if expired {
+                info!("Session of client {} expired", x.id);
+            }
+            !expired
+        });
+    }
+
+    fn connected(&self) -> impl Iterator<Item = &ClientConnection> {
+        self.connections.iter().filter(|x| x.is_connected())
+    }
+
+    pub async fn send_to_client(&self, client_id: u32, response: ClientResponse) {
+        let Some(connection) = self.client(client_id).filter(|x| x.is_connected()) else {
+            return;
+        };
+        if let Err(err) = connection.send.send(response.clone()).await {
+            error!("failed to send {:?} to client {}: {}", response, connection.id, err);
+        }
+    }
+
+    /// Answer a request of the client. Rejected messages without request id are only logged.
+    pub async fn respond(&self, client_id: u32, request_id: Option<u32>, result: Result<(), ErrorCode>) {
+        let response = match (request_id, result) {
+            (Some(request_id), Ok(())) => ClientResponse::Ack { request_id },
+            (request_id, Err(code)) => {
+                warn!("Rejected message of client {}: {}", client_id, code);
+                if request_id.is_none() {
+                    return;
+                }
+                ClientResponse::Error {
+                    request_id,
+                    code,
+                    message: code.to_string(),
+                }
+            }
+            (None, Ok(())) => return,
+        };
+        self.send_to_client(client_id, response).await;
+    }
+
+    /// Send a response to all clients of the team.
+    async fn send_to_team(&self, team_id: u32, response: ClientResponse) {
+        for connection in self.connected().filter(|x| x.team_id == team_id) {
+            if let Err(err) = connection.send.send(response.clone()).await {
+                error!("failed to send {:?} to client {}: {}", response, connection.id, err);
+            }
+        }
+    }
+
+    async fn send_to_detectives(&self, response: ClientResponse) {
+        for ts in self.teams.iter().filter(|ts| ts.team.kind == TeamKind::Detective) {
+            self.send_to_team(ts.team.id, response.clone()).await;
+        }
+    }
+
+    /// Send a response to the observers, i.e. the game master.
+    async fn send_to_observers(&self, response: ClientResponse) {
+        for connection in self.connected().filter(|x| x.observer) {
+            if let Err(err) = connection.send.send(response.clone()).await {
+                error!("failed to send {:?} to client {}: {}", response, connection.id, err);
+            }
+        }
+    }
+
+    /// Send a response to all clients.
+    pub async fn broadcast(&self, response: ClientResponse) {
+        for connection in self.connected() {
+            if let Err(err) = connection.send.send(response.clone()).await {
+                error!("failed to send {:?} to client {}: {}", response, connection.id, err);
+            }
+        }
+    }
+
+    /// Handle a detective's claim to have caught Mr. X by asking Mr. X to confirm it.
+    pub async fn claim_capture(&mut self, client_id: u32) -> Result<(), ErrorCode> {
+        if self.game.phase != GamePhase::Running {
+            return Err(ErrorCode::InvalidPhase { phase: self.game.phase });
+        }
+        if self.game.pending_capture.is_some() {
+            return Err(ErrorCode::CaptureAlreadyClaimed);
+        }
+        let team = self.team_by_client_id(client_id)?;
+        if team.team.kind != TeamKind::Detective {
+            return Err(ErrorCode::NotDetective);
+        }
+        let Some(mr_x) = self.mr_x() else {
+            return Err(ErrorCode::TeamNotFound);
+        };
+        let (team_id, mr_x_id) = (team.team.id, mr_x.team.id);
+        if !capture::can_capture(&self.config.capture, team, mr_x) {
+            info!("Rejected capture claim of team {}: not close enough to Mr. X", team_id);
+            return Err(ErrorCode::TooFarFromMrX);
+        }
+
+        info!("Team {} claims to have caught Mr. X", team_id);
+        self.game.pending_capture = Some(team_id);
+        let response = ClientResponse::CaptureClaimed { team_id };
+        self.send_to_team(mr_x_id, response.clone()).await;
+        self.send_to_team(team_id, response).await;
+        Ok(())
+    }
+
+    /// Handle Mr. X's answer to the pending capture claim.
+    pub async fn answer_capture(
+        &mut self,
+        client_id: u32,
+        confirm: bool,
+        time: DateTime<Utc>,
+    ) -> Result<(), ErrorCode> {
+        if self.team_by_client_id(client_id)?.team.kind != TeamKind::MrX {
+            return Err(ErrorCode::NotMrX);
+        }
+        let Some(team_id) = self.game.pending_capture.take() else {
+            return Err(ErrorCode::NoPendingCapture);
+        };
+        if confirm {
+            info!("Mr. X confirmed the capture by team {}", team_id);
+            let _ = self.game.finish(time, Some(GameResult::DetectivesWin { team_id }));
+        } else {
+            info!("Mr. X rejected the capture by team {}", team_id);
+            self.send_to_team(team_id, ClientResponse::CaptureRejected { team_id })
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Snapshot Mr. X's position if the reveal schedule says so.
+    fn update_reveals(&mut self, time: DateTime<Utc>) {
+        if self.game.phase != GamePhase::Running {
+            return;
+        }
+        let elapsed = self.game.elapsed(time);
+        let Some(reveal_time) = self.config.reveal_schedule.reveal_time(self.game.reveals.len()) else {
+            return;
+        };
+        if elapsed < reveal_time {
+            return;
+        }
+        let Some(mr_x) = self.mr_x() else {
+            return;
+        };
+        let reveal = MrXReveal {
+            time,
+            long: mr_x.long,
+            lat: mr_x.lat,
+            on_train: mr_x.on_train,
+        };
+        info!("Revealing Mr. X at {}, {}", reveal.lat, reveal.long);
+        self.game.reveals.push(reveal);
+    }
+
+    /// Mr. X as the detectives see him: frozen at the position of the last reveal.
+    fn revealed_mr_x(&self) -> Option<TeamState> {
+        let mr_x = self.mr_x()?;
+        if self.config.reveal_out_of_bounds && self.out_of_bounds.contains(&mr_x.team.id) {
+            return Some(TeamState {
+                players: mr_x.players.iter().map(Player::without_position).collect(),
+                ..mr_x.clone()
+            });
+        }
+        let reveal = self.game.reveals.last()?;
+        Some(TeamState {
+            team: mr_x.team.clone(),
+            long: reveal.long,
+            lat: reveal.lat,
+            on_train: reveal.on_train,
+            tickets: mr_x.tickets.clone(),
+            players: mr_x.players.iter().map(Player::without_position).collect(),
+        })
+    }
+
+    /// Put the client's team on a train, using up one of the team's tickets if they are limited.
+    pub fn embark(
+        &mut self,
+        client_id: u32,
+        train_id: u32,
+        trains: &[Train],
+        black_ticket: bool,
+        time: DateTime<Utc>,
+    ) -> Result<(), ErrorCode> {
+        let team_id = self.movable_team_mut_by_client_id(client_id)?.team.id;
+        let train = trains
+            .iter()
+            .find(|x| x.id == train_id)
+            .ok_or(ErrorCode::UnknownTrain)?;
+        self.embark_team(team_id, train, black_ticket, time)
+    }
+
+    fn embark_team(
+        &mut self,
+        team_id: u32,
+        train: &Train,
+        black_ticket: bool,
+        time: DateTime<Utc>,
+    ) -> Result<(), ErrorCode> {
+        let team = self
+            .teams
+            .iter_mut()
+            .find(|ts| ts.team.id == team_id)
+            .ok_or(ErrorCode::TeamNotFound)?;
+        let position = team.position();
+        let at_served_stop = kvv::nearest_stop(position)
+            .is_some_and(|stop| stop.id == train.stop_id && position.distance(stop.position()) <= EMBARK_RADIUS);
+        if position.distance(train.position()) > EMBARK_RADIUS && !at_served_stop {
+            return Err(ErrorCode::TooFarFromTrain);
+        }
+        let ticket = if black_ticket {
+            Ticket::Black
+        } else {
+            Ticket::from(train.mode)
+        };
+        if let Some(tickets) = &mut team.tickets {
+            if !tickets.take(ticket) {
+                return Err(ErrorCode::NoTicket { ticket });
+            }
+        }
+        team.on_train = Some(train.id);
+
+        // the detectives always learn which ticket Mr. X used
+        if team.team.kind == TeamKind::MrX {
+            self.game.mr_x_tickets.push(UsedTicket { time, ticket });
+        }
+        Ok(())
+    }
+
+    /// Advance the game to `time` and move the teams on trains along with their train.
+    pub fn update(&mut self, time: DateTime<Utc>, trains: &[Train]) {
+        self.game.update(time, self.config.time_limit());
+
+        let Self { teams, connections, .. } = self;
+        for player in teams.iter_mut().flat_map(|ts| ts.players.iter_mut()) {
+            let devices = connections
+                .iter()
+                .filter(|x| x.is_connected() && x.player_id == Some(player.id))
+                .count();
+            player.devices = devices as u32;
+        }
+
+        let Self { teams, game, .. } = self;
+        for team in teams.iter_mut().filter(|ts| game.can_move(ts.team.kind)) {
+            if let Some(train_id) = team.on_train {
+                if let Some(train) = trains.iter().find(|x| x.id == train_id) {
+                    team.long = train.long;
+                    team.lat = train.lat;
+                }
+            }
+        }
+
+        self.update_reveals(time);
+    }
+
+    /// Take the teams off trains that finished their journey or aren't running anymore, leaving them at the nearest
+    /// stop.
+    pub async fn end_journeys(&mut self, trains: &[Train]) {
+        let mut ended = Vec::new();
+        for team in self.teams.iter_mut() {
+            let Some(train_id) = team.on_train.filter(|&id| !trains.iter().any(|x| x.id == id)) else {
+                continue;
+            };
+            team.on_train = None;
+            if let Some(stop) = kvv::nearest_stop(team.position()) {
+                team.long = stop.lon as f32;
+                team.lat = stop.lat as f32;
+            }
+            info!(
+                "Team {} left train {} at the end of its journey",
+                team.team.id, train_id
+            );
+            ended.push((team.team.id, train_id));
+        }
+        for (team_id, train_id) in ended {
+            let reason = DisembarkReason::JourneyEnded;
+            self.send_to_team(team_id, ClientResponse::Disembarked { train_id, reason })
+                .await;
+        }
+    }
+
+    /// Tell the teams and the observers when a team leaves or returns to the play area.
+    pub async fn check_play_area(&mut self) {
+        let Some(area) = &self.config.play_area else {
+            self.out_of_bounds.clear();
+            return;
+        };
+        let changed: Vec<_> = self
+            .teams
+            .iter()
+            .filter(|ts| self.game.can_move(ts.team.kind))
+            // teams that never sent their position are at (0, 0)
+            .filter(|ts| ts.long != 0. || ts.lat != 0.)
+            .map(|ts| (ts.team.id, !area.contains(ts.position())))
+            .filter(|&(team_id, outside)| outside != self.out_of_bounds.contains(&team_id))
+            .collect();
+
+        for (team_id, outside) in changed {
+            let response = if outside {
+                warn!("Team {} left the play area of game {}", team_id, self.id);
+                self.out_of_bounds.insert(team_id);
+                ClientResponse::LeftPlayArea { team_id }
+            } else {
+                info!("Team {} returned to the play area of game {}", team_id, self.id);
+                self.out_of_bounds.remove(&team_id);
+                ClientResponse::ReturnedToPlayArea { team_id }
+            };
+            self.send_to_team(team_id, response.clone()).await;
+            self.send_to_observers(response).await;
+        }
+    }
+
+    /// Tell the detectives which stops Mr. X could have reached since his last reveal, whenever he is revealed and
+    /// every `REACHABLE_AREA_INTERVAL` after that.
+    pub async fn update_reachable_area(&mut self, time: DateTime<Utc>, departures: &kvv::LineDepartures) {
+        let reveal = self.game.reveals.last().filter(|_| self.config.reachable_area).cloned();
+        let Some(reveal) = reveal else {
+            // clear the area the detectives were shown
+            if let Some(since) = self.reachable_area_sent.take() {
+                let response = ClientResponse::ReachableArea {
+                    since,
+                    stops: Vec::new(),
+                };
+                self.send_to_detectives(response).await;
+            }
+            return;
+        };
+        if self.game.phase != GamePhase::Running {
+            return;
+        }
+        let interval = chrono::Duration::from_std(REACHABLE_AREA_INTERVAL).unwrap_or_default();
+        if self
+            .reachable_area_sent
+            .is_some_and(|sent| sent >= reveal.time && time - sent < interval)
+        {
+            return;
+        }
+        let Some(graph) = kvv::KVV_STOP_GRAPH.get() else {
+            return;
+        };
+        self.reachable_area_sent = Some(time);
+
+        let start = Point {
+            latitude: reveal.lat,
+            longitude: reveal.long,
+        };
+        let stops = reachability::reachable_stops(start, reveal.on_train, reveal.time, time, departures, graph);
+        let response = ClientResponse::ReachableArea {
+            since: reveal.time,
+            stops,
+        };
+        self.send_to_detectives(response).await;
+    }
+
+    /// Detect teams getting on or off trains without telling, and embark or disembark them if configured to.
+    pub async fn detect_trains(&mut self, time: DateTime<Utc>, trains: &[Train]) {
+        let detection = self.config.train_detection;
+        if detection == TrainDetection::Off {
+            return;
+        }
+        self.train_tracks.record(time, trains);
+
+        let Self {
+            teams,
+            game,
+            train_tracks,
+            train_detectors,
+            ..
+        } = self;
+        let detections: Vec<_> = teams
+            .iter()
+            .filter(|ts| game.can_move(ts.team.kind))
+            .filter_map(|ts| {
+                let detector = train_detectors.entry(ts.team.id).or_default();
+                let detected = detector.detect(time, ts.on_train, train_tracks)?;
+                Some((ts.team.id, ts.team.kind, detected))
+            })
+            .collect();
+
+        for (team_id, kind, detected) in detections {
+            let automatic = detection == TrainDetection::Auto && kind == TeamKind::Detective;
+            let response = match detected {
+                Detection::Embarked(train_id) => {
+                    let train = trains.iter().find(|x| x.id == train_id);
+                    if automatic && train.is_some_and(|train| self.embark_team(team_id, train, false, time).is_ok()) {
+                        info!("Team {} embarked train {} automatically", team_id, train_id);
+                        ClientResponse::Embarked { train_id }
+                    } else {
+                        ClientResponse::TrainDetected { train_id }
+                    }
+                }
+                Detection::Left(train_id) => {
+                    if let Some(team) = self
+                        .teams
+                        .iter_mut()
+                        .find(|ts| ts.team.id == team_id)
+                        .filter(|_| automatic)
+                    {
+                        team.on_train = None;
+                        info!("Team {} left train {} automatically", team_id, train_id);
+                        let reason = DisembarkReason::LeftTrain;
+                        ClientResponse::Disembarked { train_id, reason }
+                    } else {
+                        ClientResponse::TrainLeft { train_id }
+                    }
+                }
+            };
+            self.send_to_team(team_id, response).await;
+        }
+    }
+
+    /// The violations since the last call, to write them to the game log.
+    pub fn unlogged_violations(&mut self) -> &[PositionViolation] {
+        let logged = self.logged_violations;
+        self.logged_violations = self.game.violations.len();
+        self.game.violations.get(logged..).unwrap_or_default()
+    }
+
+    /// The full game state, without hiding anything.
+    pub fn game_state(&self, trains: Vec<Train>) -> GameState {
+        GameState {
+            teams: self.teams.clone(),
+            trains,
+            reveals: self.game.reveals.clone(),
+            mr_x_tickets: self.game.mr_x_tickets.clone(),
+        }
+    }
+
+    /// Tell a newly connected client about the current phase, later changes are announced by `announce_phase`.
+    pub fn greet(&self, connection: &ClientConnection) {
+        let (client_id, send) = (connection.id, &connection.send);
+        let session = ClientResponse::Session {
+            token: connection.session_token.clone(),
+            team_id: connection.team_id,
+        };
+        if let Err(err) = send.try_send(session) {
+            warn!("failed to send session to client {}: {}", client_id, err);
+        }
+        if let Err(err) = send.try_send(ClientResponse::GamePhase(self.game.phase)) {
+            warn!("failed to send game phase to client {}: {}", client_id, err);
+        }
+        if let Some(result) = self.game.result {
+            if let Err(err) = send.try_send(ClientResponse::GameOver(result)) {
+                warn!("failed to send game result to client {}: {}", client_id, err);
+            }
+        }
+        for message in self.chat_history(connection.team_id) {
+            if let Err(err) = send.try_send(ClientResponse::Chat(message.clone())) {
+                warn!("failed to send chat history to client {}: {}", client_id, err);
+                break;
+            }
+        }
+    }
+
+    /// The recent chat messages visible to the team.
+    fn chat_history(&self, team_id: u32) -> impl Iterator<Item = &ChatMessage> {
+        let visible: Vec<_> = self.chat.iter().filter(|x| x.is_visible_to(team_id)).collect();
+        let skip = visible.len().saturating_sub(CHAT_HISTORY_LEN);
+        visible.into_iter().skip(skip)
+    }
+
+    /// Send the chat history to a client, e.g. after it joined a team.
+    pub async fn send_chat_history(&self, client_id: u32) {
+        let Some(connection) = self.client(client_id) else {
+            return;
+        };
+        for message in self.chat_history(connection.team_id) {
+            self.send_to_client(client_id, ClientResponse::Chat(message.clone()))
+                .await;
+        }
+    }
+
+    /// Send a chat message of the client to everyone who may read it.
+    pub async fn chat(
+        &mut self,
+        client_id: u32,
+        channel: ChatChannel,
+        text: String,
+        time: DateTime<Utc>,
+    ) -> Result<(), ErrorCode> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ErrorCode::EmptyMessage);
+        }
+        if text.chars().count() > MAX_CHAT_MESSAGE_LEN {
+            return Err(ErrorCode::MessageTooLong {
+                max_len: MAX_CHAT_MESSAGE_LEN as u32,
+            });
+        }
+        let team = self.team_by_client_id(client_id)?;
+        if channel == ChatChannel::Taunt && team.team.kind != TeamKind::MrX {
+            return Err(ErrorCode::NotMrX);
+        }
+        if channel == ChatChannel::Announcement {
+            return Err(ErrorCode::NotAdmin);
+        }
+        let player_id = self.client(client_id).and_then(|client| client.player_id);
+        let message = ChatMessage {
+            time,
+            channel,
+            team_id: team.team.id,
+            player_name: team
+                .players
+                .iter()
+                .find(|player| Some(player.id) == player_id)
+                .map(|player| player.name.clone()),
+            text: text.to_owned(),
+        };
+        self.post(message).await;
+        Ok(())
+    }
+
+    /// Send a chat message to everyone who may read it and add it to the history.
+    async fn post(&mut self, message: ChatMessage) {
+        for connection in self.connected().filter(|x| message.is_visible_to(x.team_id)) {
+            if let Err(err) = connection.send.send(ClientResponse::Chat(message.clone())).await {
+                error!("failed to send chat message to client {}: {}", connection.id, err);
+            }
+        }
+        self.chat.push(message);
+        self.trim_chat();
+    }
+
+    /// Forget the chat messages that are no longer part of the history of any team, so the room file doesn't grow.
+    fn trim_chat(&mut self) {
+        // the number of newer messages in the global channels and in the team channel of each team
+        let mut global = 0;
+        let mut team = HashMap::<u32, usize>::new();
+        let mut keep: Vec<_> = self
+            .chat
+            .iter()
+            .rev()
+            .map(|message| {
+                if message.channel == ChatChannel::Team {
+                    let newer = team.entry(message.team_id).or_default();
+                    *newer += 1;
+                    global + *newer <= CHAT_HISTORY_LEN
+                } else {
+                    global += 1;
+                    global <= CHAT_HISTORY_LEN
+                }
+            })
+            .collect();
+        keep.reverse();
+        let mut keep = keep.into_iter();
+        self.chat.retain(|_| keep.next().unwrap_or(true));
+    }
+
+    /// Send an announcement of the game master to all clients.
+    pub async fn announce(&mut self, text: &str, time: DateTime<Utc>) -> Result<(), GameError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(GameError::EmptyMessage);
+        }
+        info!("Announcement in game {}: {}", self.id, text);
+        let message = ChatMessage {
+            time,
+            channel: ChatChannel::Announcement,
+            team_id: 0,
+            player_name: None,
+            text: text.to_owned(),
+        };
+        self.post(message).await;
+        Ok(())
+    }
+
+    fn team_mut(&mut self, team_id: u32) -> Result<&mut TeamState, GameError> {
+        self.teams
+            .iter_mut()
+            .find(|ts| ts.team.id == team_id)
+            .ok_or(GameError::TeamNotFound)
+    }
+
+    /// Rename or recolour a team.
+    pub fn update_team(&mut self, team_id: u32, update: UpdateTeam) -> Result<Team, GameError> {
+        let name = update.name.as_deref().map(str::trim);
+        if name.is_some_and(str::is_empty) {
+            return Err(GameError::InvalidName);
+        }
+        if let Some(name) = name {
+            if self
+                .teams
+                .iter()
+                .any(|ts| ts.team.id != team_id && ts.team.name == name)
+            {
+                return Err(GameError::NameAlreadyExists);
+            }
+        }
+        let room_id = self.id;
+        let team = &mut self.team_mut(team_id)?.team;
+        if let Some(name) = name {
+            team.name = name.to_owned();
+        }
+        if let Some(color) = update.color {
+            team.color = color;
+        }
+        info!("Updated team {} in game {}: {:?}", team_id, room_id, team);
+        Ok(team.clone())
+    }
+
+    /// Remove a team, its members have to join another team.
+    pub fn delete_team(&mut self, team_id: u32) -> Result<(), GameError> {
+        if self.team_mut(team_id)?.team.kind == TeamKind::MrX {
+            return Err(GameError::MrXRequired);
+        }
+        self.teams.retain(|ts| ts.team.id != team_id);
+        self.join_codes.remove(&team_id);
+        self.out_of_bounds.remove(&team_id);
+        self.train_detectors.remove(&team_id);
+        for connection in self.connections.iter_mut().filter(|x| x.team_id == team_id) {
+            connection.team_id = 0;
+            connection.player_id = None;
+        }
+        info!("Deleted team {} in game {}", team_id, self.id);
+        Ok(())
+    }
+
+    /// Move a team, e.g. when its position is wrong. The team leaves its train.
+    pub fn force_position(&mut self, team_id: u32, long: f32, lat: f32) -> Result<(), GameError> {
+        let team = self.team_mut(team_id)?;
+        team.long = long;
+        team.lat = lat;
+        team.on_train = None;
+        info!("Moved team {} in game {} to {}, {}", team_id, self.id, lat, long);
+        Ok(())
+    }
+
+    /// Start the game from the lobby and hand out the tickets of the config.
+    pub fn start(&mut self, time: DateTime<Utc>) -> Result<(), InvalidTransition> {
+        self.game.start(time, self.config.head_start())?;
+        if let Some(tickets) = &self.config.tickets {
+            for team in self.teams.iter_mut() {
+                team.tickets = tickets.for_kind(team.team.kind);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reset the game to the lobby, keeping the teams.
+    pub fn reset(&mut self) {
+        self.game = Game::default();
+        for team in self.teams.iter_mut() {
+            team.on_train = None;
+            team.tickets = None;
+        }
+        // the clients drop the reachable area with the reveals
+        self.out_of_bounds.clear();
+        self.train_detectors.clear();
+        self.reachable_area_sent = None;
+        info!("Reset game {}", self.id);
+    }
+
+    /// Close the connection of a client and end its session.
+    pub fn kick(&mut self, client_id: u32) -> Result<(), GameError> {
+        let len = self.connections.len();
+        // dropping the connection's sender closes the socket
+        self.connections.retain(|x| x.id != client_id);
+        if self.connections.len() == len {
+            return Err(GameError::ClientNotFound);
+        }
+        info!("Kicked client {} from game {}", client_id, self.id);
+        Ok(())
+    }
+
+    pub fn connection_infos(&self) -> Vec<ConnectionInfo> {
+        self.connections
+            .iter()
+            .map(|x| ConnectionInfo {
+                id: x.id,
+                team_id: x.team_id,
+                player_id: x.player_id,
+                connected: x.is_connected(),
+            })
+            .collect()
+    }
+
+    pub fn join_codes(&self) -> Vec<TeamJoinCode> {
+        self.teams
+            .iter()
+            .filter_map(|ts| {
+                Some(TeamJoinCode {
+                    team_id: ts.team.id,
+                    join_code: self.join_code(ts.team.id)?.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Announce phase changes since the last call to all clients.
+    pub async fn announce_phase(&mut self) {
+        if self.game.phase == self.announced_phase {
+            return;
+        }
+        self.announced_phase = self.game.phase;
+        self.broadcast(ClientResponse::GamePhase(self.game.phase)).await;
+        if let Some(result) = self.game.result.filter(|_| self.game.phase == GamePhase::Finished) {
+            info!("Game {} is over: {:?}", self.id, result);
+            self.broadcast(ClientResponse::GameOver(result)).await;
+        }
+    }
+
+    /// Send the game state to all clients, hiding Mr. X from everyone but Mr. X himself.
+    pub async fn send_game_state(&mut self, time: DateTime<Utc>, game_state: &GameState) {
+        // the feed is only needed while someone watches, a new observer waits for the delay like everyone else
+        if self.connected().any(|x| x.observer) {
+            self.observer_feed.push_back((time, game_state.clone()));
+        } else {
+            self.observer_feed.clear();
+        }
+        let delay = chrono::Duration::from_std(self.config.observer_delay()).unwrap_or_default();
+        // keep the newest state that is old enough and the states after it
+        while self.observer_feed.get(1).is_some_and(|(at, _)| *at <= time - delay) {
+            self.observer_feed.pop_front();
+        }
+        let observer_state = self
+            .observer_feed
+            .front()
+            .filter(|(at, _)| *at <= time - delay)
+            .map(|(_, state)| state);
+
+        let revealed_mr_x = self.revealed_mr_x();
+        for connection in self.connections.iter_mut().filter(|x| x.is_connected()) {
+            let game_state = if connection.observer {
+                let Some(observer_state) = observer_state else {
+                    continue;
+                };
+                observer_state.clone()
+            } else {
+                let mut teams: Vec<_> = game_state
+                    .teams
+                    .iter()
+                    .filter(|ts| ts.team.kind == TeamKind::Detective || ts.team.id == connection.team_id)
+                    .cloned()
+                    .collect();
+                if let Some(mr_x) = &revealed_mr_x {
+                    if mr_x.team.id != connection.team_id {
+                        teams.push(mr_x.clone());
+                    }
+                }
+                GameState {
+                    teams,
+                    trains: game_state.trains.clone(),
+                    reveals: game_state.reveals.clone(),
+                    mr_x_tickets: game_state.mr_x_tickets.clone(),
+                }
+            };
+            let Some(response) = connection.updates.encode(game_state) else {
+                continue;
+            };
+            if let Err(err) = connection.send.send(response).await {
+                error!("failed to send game state to client {}: {}", connection.id, err);
+                continue;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::area::PlayArea;
+
+    const CLIENT_ID: u32 = 1;
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 10, 11, 12, 0, 0).unwrap() + chrono::Duration::seconds(seconds)
+    }
+
+    /// A room with a detective team that the client joined.
+    fn room() -> (Room, u32) {
+        let mut room = Room::new(1, "Test".to_owned(), GameConfig::default());
+        let team_id = room.team_id_gen.next();
+        let join_code = room.add_team(Team {
+            id: team_id,
+            name: "Detectives".to_owned(),
+            color: "#ffffff".to_owned(),
+            kind: TeamKind::Detective,
+        });
+        let (send, _) = tokio::sync::mpsc::channel(100);
+        room.connections.push(ClientConnection::new(CLIENT_ID, send));
+        room.join_team(CLIENT_ID, team_id, &join_code, None).unwrap();
+        (room, team_id)
+    }
+
+    /// A position `north` meters north of the start.
+    fn update(north: f32, accuracy: f32) -> PositionUpdate {
+        PositionUpdate {
+            long: 8.4,
+            lat: 49.0 + north / 111_320.,
+            accuracy: Some(accuracy),
+            heading: None,
+            speed: None,
+            timestamp: None,
+        }
+    }
+
+    fn team_north(room: &Room, team_id: u32) -> f32 {
+        let team = room.teams.iter().find(|ts| ts.team.id == team_id).unwrap();
+        (team.lat - 49.0) * 111_320.
+    }
+
+    #[test]
+    fn test_speed_check_on_filtered_positions() {
+        let (mut room, team_id) = room();
+        for i in 0..10 {
+            room.update_position(CLIENT_ID, &update(0., 10.), time(i)).unwrap();
+        }
+
+        // a single inaccurate fix far away barely moves the filtered position, so it isn't a violation
+        room.update_position(CLIENT_ID, &update(300., 90.), time(10)).unwrap();
+        assert!(team_north(&room, team_id) < 10.);
+        assert!(room.game.violations.is_empty());
+
+        // an accurate fix is followed, but the team can't have walked that far
+        let result = room.update_position(CLIENT_ID, &update(1000., 5.), time(11));
+        assert!(matches!(result, Err(ErrorCode::ImplausiblePosition)));
+        assert_eq!(room.game.violations.len(), 1);
+        assert!(team_north(&room, team_id) < 10.);
+
+        // the rejected fix didn't move the filter
+        room.update_position(CLIENT_ID, &update(0., 10.), time(12)).unwrap();
+        assert!(team_north(&room, team_id) < 10.);
+    }
+
+    #[test]
+    fn test_rotate_join_code() {
+        let (mut room, team_id) = room();
+        let join_code = room.join_code(team_id).unwrap().to_owned();
+        room.join_team(CLIENT_ID, team_id, &join_code, Some("Alice")).unwrap();
+        room.update(time(0), &[]);
+        let team = room.teams.iter().find(|ts| ts.team.id == team_id).unwrap();
+        assert_eq!(team.players[0].devices, 1);
+
+        let new_code = room.rotate_join_code(team_id);
+        assert_ne!(new_code, join_code);
+        room.update(time(1), &[]);
+        let team = room.teams.iter().find(|ts| ts.team.id == team_id).unwrap();
+        assert_eq!(team.players[0].devices, 0);
+        assert!(room.client(CLIENT_ID).unwrap().player_id.is_none());
+        assert!(room.join_team(CLIENT_ID, team_id, &join_code, Some("Alice")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chat_is_trimmed() {
+        let (mut room, team_id) = room();
+        let message = |channel, team_id, i: i64| ChatMessage {
+            time: time(i),
+            channel,
+            team_id,
+            player_name: None,
+            text: i.to_string(),
+        };
+        for i in 0..40 {
+            room.post(message(ChatChannel::Team, team_id, i)).await;
+        }
+        for i in 40..100 {
+            room.post(message(ChatChannel::Global, team_id, i)).await;
+            room.post(message(ChatChannel::Team, team_id + 1, i)).await;
+        }
+        for i in 100..130 {
+            room.post(message(ChatChannel::Team, team_id, i)).await;
+        }
+
+        let history = |team_id| room.chat_history(team_id).map(|x| x.text.clone()).collect::<Vec<_>>();
+        let expected: Vec<_> = (80..130).map(|i| i.to_string()).collect();
+        assert_eq!(history(team_id), expected);
+        let expected: Vec<_> = (75..100).flat_map(|i| [i.to_string(), i.to_string()]).collect();
+        assert_eq!(history(team_id + 1), expected);
+        let expected: Vec<_> = (50..100).map(|i| i.to_string()).collect();
+        assert_eq!(history(0), expected);
+        // the last 50 global messages, the last 25 of the other team and none of the first messages of the team
+        assert_eq!(room.chat.len(), 50 + 25 + 30);
+    }
+
+    #[tokio::test]
+    async fn test_observer_feed() {
+        let (mut room, _) = room();
+        room.config.observer_delay_seconds = 10;
+        let game_state = room.game_state(Vec::new());
+        for i in 0..100 {
+            room.send_game_state(time(i), &game_state).await;
+        }
+        assert!(room.observer_feed.is_empty());
+
+        let (send, _) = tokio::sync::mpsc::channel(100);
+        room.connections.push(ClientConnection::new(CLIENT_ID + 1, send));
+        room.connections.last_mut().unwrap().observer = true;
+        for i in 100..200 {
+            room.send_game_state(time(i), &game_state).await;
+        }
+        // the state of 10 seconds ago and the states after it
+        assert_eq!(room.observer_feed.len(), 11);
+
+        room.connections.pop();
+        room.send_game_state(time(200), &game_state).await;
+        assert!(room.observer_feed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_team_state_is_cleared() {
+        let (mut room, team_id) = room();
+        room.config.play_area = Some(PlayArea::Polygon {
+            coordinates: vec![vec![[9.0, 50.0], [9.1, 50.0], [9.1, 50.1], [9.0, 50.0]]],
+        });
+        room.update_position(CLIENT_ID, &update(0., 10.), time(0)).unwrap();
+        room.check_play_area().await;
+        room.reachable_area_sent = Some(time(0));
+        assert!(room.out_of_bounds.contains(&team_id));
+        assert!(room.train_detectors.contains_key(&team_id));
+
+        room.reset();
+        assert!(room.out_of_bounds.is_empty());
+        assert!(room.train_detectors.is_empty());
+        assert!(room.reachable_area_sent.is_none());
+
+        room.update_position(CLIENT_ID, &update(0., 10.), time(1)).unwrap();
+        room.check_play_area().await;
+        room.delete_team(team_id).unwrap();
+        assert!(!room.out_of_bounds.contains(&team_id));
+        assert!(!room.train_detectors.contains_key(&team_id));
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use trias::response::{GeoPosition, Service};
+use trias::trip::{InterchangeLeg, LegCall, TimedLeg, TripResult};
+use trias::{RequestPayload, TripLocation, TripRequestBuilder};
+
+use crate::kvv;
+use crate::ws_message::TransportMode;
+
+/// A request to plan trips, e.g. from a detective to where Mr. X was seen.
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct TripQuery {
+    pub origin: Place,
+    pub destination: Place,
+    #[serde(default)]
+    pub via: Vec<Place>,
+    /// Plan trips departing at this time, now if neither this nor `arrival_time` is set.
+    pub departure_time: Option<DateTime<Utc>>,
+    /// Plan trips arriving until this time.
+    pub arrival_time: Option<DateTime<Utc>>,
+    /// The modes of transport to use, all of them if empty.
+    #[serde(default)]
+    pub modes: Vec<TransportMode>,
+    pub max_interchanges: Option<u32>,
+}
+
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub enum Place {
+    Stop { id: String },
+    Position { long: f32, lat: f32 },
+}
+
+impl From<Place> for TripLocation {
+    fn from(place: Place) -> Self {
+        match place {
+            Place::Stop { id } => TripLocation::Stop(id),
+            Place::Position { long, lat } => TripLocation::Position(GeoPosition {
+                longitude: long as f64,
+                latitude: lat as f64,
+            }),
+        }
+    }
+}
+
+impl TripQuery {
+    pub fn request(self) -> RequestPayload {
+        let mut builder = TripRequestBuilder::new(self.origin.into(), self.destination.into());
+        for via in self.via {
+            builder = builder.via(via.into());
+        }
+        if let Some(departure_time) = self.departure_time {
+            builder = builder.departure_time(departure_time);
+        }
+        if let Some(arrival_time) = self.arrival_time {
+            builder = builder.arrival_time(arrival_time);
+        }
+        if !self.modes.is_empty() {
+            let modes = self
+                .modes
+                .into_iter()
+                .flat_map(pt_modes)
+                .map(|&mode| mode.to_owned())
+                .collect();
+            builder = builder.include_modes(modes);
+        }
+        if let Some(max_interchanges) = self.max_interchanges {
+            builder = builder.interchange_limit(max_interchanges);
+        }
+        builder.build()
+    }
+}
+
+/// The TRIAS `PtMode`s of a mode of transport.
+fn pt_modes(mode: TransportMode) -> &'static [&'static str] {
+    match mode {
+        TransportMode::Tram => &["tram"],
+        TransportMode::SBahn => &["rail", "urbanRail"],
+        TransportMode::Bus => &["bus", "trolleyBus", "coach"],
+    }
+}
+
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub struct TripPlan {
+    pub departure: DateTime<Utc>,
+    pub arrival: DateTime<Utc>,
+    pub interchanges: u32,
+    pub legs: Vec<TripLeg>,
+}
+
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub enum TripLeg {
+    Ride {
+        line_name: String,
+        mode: TransportMode,
+        direction: String,
+        from: LegStop,
+        to: LegStop,
+        departure: Option<DateTime<Utc>>,
+        arrival: Option<DateTime<Utc>>,
+    },
+    Walk {
+        from: String,
+        to: String,
+        departure: DateTime<Utc>,
+        arrival: DateTime<Utc>,
+        /// The length in meters.
+        length: Option<u32>,
+    },
+}
+
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub struct LegStop {
+    pub id: String,
+    pub name: String,
+}
+
+impl From<TripResult> for TripPlan {
+    fn from(result: TripResult) -> Self {
+        let trip = result.trip;
+        let legs = trip
+            .trip_leg
+            .into_iter()
+            .filter_map(|leg| {
+                let walk = leg.interchange_leg.or(leg.continuous_leg);
+                leg.timed_leg.map(ride).or_else(|| walk.map(self::walk))
+            })
+            .collect();
+        Self {
+            departure: trip.start_time,
+            arrival: trip.end_time,
+            interchanges: trip.interchanges,
+            legs,
+        }
+    }
+}
+
+fn ride(leg: TimedLeg) -> TripLeg {
+    let service = leg.service;
+    TripLeg::Ride {
+        line_name: service.service_section.published_line_name.text.clone(),
+        mode: kvv::transport_mode(&service.service_section),
+        direction: service.destination_text.map(|text| text.text).unwrap_or_default(),
+        departure: leg.leg_board.service_departure.as_ref().map(service_time),
+        arrival: leg.leg_alight.service_arrival.as_ref().map(service_time),
+        from: stop(leg.leg_board),
+        to: stop(leg.leg_alight),
+    }
+}
+
+/// The estimated time of a call, or the timetabled time if there is no estimate.
+fn service_time(service: &Service) -> DateTime<Utc> {
+    service.estimated_time.unwrap_or(service.timetabled_time)
+}
+
+fn stop(call: LegCall) -> LegStop {
+    LegStop {
+        id: call.stop_point_ref,
+        name: call.stop_point_name.text,
+    }
+}
+
+fn walk(leg: InterchangeLeg) -> TripLeg {
+    TripLeg::Walk {
+        from: leg.leg_start.location_name.text,
+        to: leg.leg_end.location_name.text,
+        departure: leg.time_window_start,
+        arrival: leg.time_window_end,
+        length: leg.length,
+    }
+}
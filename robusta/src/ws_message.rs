@@ -1,39 +1,382 @@
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::config::GameConfig;
+use crate::point::Point;
+use crate::reachability::ReachableStop;
+
+/// A message with a request id, which the server answers with `Ack` or `Error`.
+/// Plain `ClientMessage`s are accepted too, but rejections of them are only logged.
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct ClientRequest {
+    pub request_id: Option<u32>,
+    pub message: ClientMessage,
+}
+
+impl ClientRequest {
+    /// The request id of a message that couldn't be parsed, so the client can match the error to its request.
+    pub fn request_id_of(json: &str) -> Option<u32> {
+        let value: serde_json::Value = serde_json::from_str(json).ok()?;
+        value.get("request_id")?.as_u64()?.try_into().ok()
+    }
+}
+
+impl From<ClientMessage> for ClientRequest {
+    fn from(message: ClientMessage) -> Self {
+        Self {
+            request_id: None,
+            message,
+        }
+    }
+}
 
 #[derive(specta::Type, Clone, Deserialize, Debug)]
 pub enum ClientMessage {
-    Position { long: f32, lat: f32 },
-    SetTeamPosition { long: f32, lat: f32 },
-    JoinTeam { team_id: u32 },
-    EmbarkTrain { train_id: String },
+    Position(PositionUpdate),
+    SetTeamPosition {
+        long: f32,
+        lat: f32,
+    },
+    JoinTeam {
+        team_id: u32,
+        join_code: String,
+        /// The name of the player, players with the same name in a team are the same player.
+        #[serde(default)]
+        player_name: Option<String>,
+    },
+    LeaveTeam,
+    EmbarkTrain {
+        train_id: u32,
+        /// Use a black ticket, which hides the mode of transport from the detectives.
+        #[serde(default)]
+        black_ticket: bool,
+    },
     DisembarkTrain,
-    Message(String),
+    /// Send a chat message.
+    Message {
+        channel: ChatChannel,
+        text: String,
+    },
+    /// Receive `GameStateDelta`s instead of the full `GameState` in every update.
+    DeltaUpdates {
+        enabled: bool,
+    },
+    /// Receive the full `GameState` with the next update.
+    Resync,
+    /// Watch the whole game, including Mr. X. Needs the admin token.
+    Observe {
+        admin_token: String,
+    },
+    /// A detective claims to have caught Mr. X.
+    ClaimCapture,
+    /// Mr. X confirms the pending capture claim.
+    ConfirmCapture,
+    /// Mr. X rejects the pending capture claim.
+    RejectCapture,
 }
 
 #[derive(specta::Type, Clone, Serialize, Deserialize, Debug)]
 pub enum ClientResponse {
+    /// Sent on connect. Reconnecting with the token (`/ws?session=<token>`) resumes the session.
+    Session {
+        token: String,
+        team_id: u32,
+    },
     GameState(GameState),
+    /// The changes since the last update, for clients that enabled delta updates.
+    GameStateDelta(GameStateDelta),
+    GamePhase(GamePhase),
+    /// A detective team claims to have caught Mr. X and waits for Mr. X to confirm.
+    CaptureClaimed {
+        team_id: u32,
+    },
+    /// Mr. X rejected the capture claim of the team.
+    CaptureRejected {
+        team_id: u32,
+    },
+    GameOver(GameResult),
+    /// The team seems to be on the train, but didn't embark.
+    TrainDetected {
+        train_id: u32,
+    },
+    /// The team seems to have left its train, but didn't disembark.
+    TrainLeft {
+        train_id: u32,
+    },
+    /// The server put the team on the train.
+    Embarked {
+        train_id: u32,
+    },
+    /// The team left the play area.
+    LeftPlayArea {
+        team_id: u32,
+    },
+    /// The team is back in the play area.
+    ReturnedToPlayArea {
+        team_id: u32,
+    },
+    /// The stops Mr. X could have reached since he was revealed at `since`, sent to the detectives. Empty if the
+    /// game master disabled it.
+    ReachableArea {
+        since: DateTime<Utc>,
+        stops: Vec<ReachableStop>,
+    },
+    /// The server took the team off the train.
+    Disembarked {
+        train_id: u32,
+        reason: DisembarkReason,
+    },
+    Chat(ChatMessage),
+    /// The request was processed successfully.
+    Ack {
+        request_id: u32,
+    },
+    /// The request was rejected, `request_id` is `None` if the message couldn't be parsed.
+    Error {
+        request_id: Option<u32>,
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+/// Why the server took a team off its train.
+#[derive(specta::Type, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum DisembarkReason {
+    /// The positions of the team moved away from the train.
+    LeftTrain,
+    /// The train reached its final stop, or isn't running anymore.
+    JourneyEnded,
+}
+
+/// Why a client message was rejected.
+#[derive(specta::Type, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ErrorCode {
+    InvalidMessage,
+    TeamNotFound,
+    WrongJoinCode,
+    /// The client has to join a team first.
+    NotInTeam,
+    /// The team of the client can't move in the current phase of the game.
+    CannotMove {
+        phase: GamePhase,
+    },
+    UnknownTrain,
+    /// The team is neither close to the train nor at the stop it serves.
+    TooFarFromTrain,
+    /// The team couldn't have reached the position in time without a train.
+    ImplausiblePosition,
+    /// The position was measured too long ago.
+    StalePosition,
+    /// The position is too inaccurate.
+    InaccuratePosition,
+    /// The coordinates are not a position on earth.
+    InvalidPosition,
+    NoTicket {
+        ticket: Ticket,
+    },
+    NotDetective,
+    NotMrX,
+    /// The request is not possible in the current phase of the game.
+    InvalidPhase {
+        phase: GamePhase,
+    },
+    CaptureAlreadyClaimed,
+    /// The detectives are not close enough to Mr. X to catch him.
+    TooFarFromMrX,
+    NoPendingCapture,
+    EmptyMessage,
+    /// Only admins can send announcements.
+    NotAdmin,
+    MessageTooLong {
+        max_len: u32,
+    },
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMessage => write!(f, "the message could not be parsed"),
+            Self::TeamNotFound => write!(f, "the team does not exist"),
+            Self::WrongJoinCode => write!(f, "the join code is wrong"),
+            Self::NotInTeam => write!(f, "join a team first"),
+            Self::CannotMove { phase } => write!(f, "the team can't move while the game is {:?}", phase),
+            Self::UnknownTrain => write!(f, "the train is unknown"),
+            Self::TooFarFromTrain => write!(f, "the train is too far away"),
+            Self::ImplausiblePosition => write!(f, "the position is too far away from the last one"),
+            Self::StalePosition => write!(f, "the position is outdated"),
+            Self::InaccuratePosition => write!(f, "the position is too inaccurate"),
+            Self::InvalidPosition => write!(f, "the position is invalid"),
+            Self::NoTicket { ticket } => write!(f, "no {:?} ticket left", ticket),
+            Self::NotDetective => write!(f, "only detectives can do this"),
+            Self::NotMrX => write!(f, "only Mr. X can do this"),
+            Self::InvalidPhase { phase } => write!(f, "not possible while the game is {:?}", phase),
+            Self::CaptureAlreadyClaimed => write!(f, "another team already claimed to have caught Mr. X"),
+            Self::TooFarFromMrX => write!(f, "Mr. X is too far away"),
+            Self::NoPendingCapture => write!(f, "nobody claimed to have caught Mr. X"),
+            Self::EmptyMessage => write!(f, "the message is empty"),
+            Self::NotAdmin => write!(f, "only admins can do this"),
+            Self::MessageTooLong { max_len } => write!(f, "the message is longer than {} characters", max_len),
+        }
+    }
+}
+
+#[derive(specta::Type, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum GameResult {
+    /// The detectives of the team caught Mr. X.
+    DetectivesWin { team_id: u32 },
+    /// Mr. X wasn't caught before the time limit expired.
+    MrXWins,
+}
+
+#[derive(specta::Type, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ChatChannel {
+    /// Only visible to the team of the sender.
+    Team,
+    /// Visible to everyone.
+    Global,
+    /// Mr. X taunting the detectives, visible to everyone.
+    Taunt,
+    /// An announcement of the game master, visible to everyone.
+    Announcement,
+}
+
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug)]
+pub struct ChatMessage {
+    pub time: DateTime<Utc>,
+    pub channel: ChatChannel,
+    pub team_id: u32,
+    /// The name of the player who sent the message, `None` for anonymous clients.
+    pub player_name: Option<String>,
+    pub text: String,
+}
+
+impl ChatMessage {
+    /// Whether the message is visible to members of the team.
+    pub fn is_visible_to(&self, team_id: u32) -> bool {
+        self.channel != ChatChannel::Team || self.team_id == team_id
+    }
+}
+
+/// The phases of a game. Games start in the lobby and end once they are finished.
+#[derive(specta::Type, Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub enum GamePhase {
+    /// Teams can be created, the game has not started yet.
+    #[default]
+    Lobby,
+    /// Mr. X's head start: only Mr. X may move until `until`.
+    Countdown {
+        until: DateTime<Utc>,
+    },
+    Running,
+    Paused,
+    Finished,
 }
 
 #[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug)]
 pub struct GameState {
     pub teams: Vec<TeamState>,
     pub trains: Vec<Train>,
+    pub reveals: Vec<MrXReveal>,
+    /// The tickets Mr. X used so far, even while his position is hidden.
+    pub mr_x_tickets: Vec<UsedTicket>,
 }
 
+/// The changes of the game state since the last update. Teams and trains are identified by their id.
 #[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug)]
+pub struct GameStateDelta {
+    /// The added or changed teams.
+    pub teams: Vec<TeamState>,
+    pub removed_teams: Vec<u32>,
+    /// The added or moved trains.
+    pub trains: Vec<Train>,
+    pub removed_trains: Vec<u32>,
+    /// The new reveals.
+    pub reveals: Vec<MrXReveal>,
+    /// The tickets Mr. X used since the last update.
+    pub mr_x_tickets: Vec<UsedTicket>,
+}
+
+impl GameStateDelta {
+    pub fn is_empty(&self) -> bool {
+        self.teams.is_empty()
+            && self.removed_teams.is_empty()
+            && self.trains.is_empty()
+            && self.removed_trains.is_empty()
+            && self.reveals.is_empty()
+            && self.mr_x_tickets.is_empty()
+    }
+}
+
+/// A snapshot of Mr. X's position, taken when he is revealed to the detectives.
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MrXReveal {
+    pub time: DateTime<Utc>,
+    pub long: f32,
+    pub lat: f32,
+    #[serde(default, deserialize_with = "deserialize_train_id")]
+    pub on_train: Option<u32>,
+}
+
+#[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct TeamState {
     pub team: Team,
     pub long: f32,
     pub lat: f32,
-    pub on_train: Option<String>,
+    /// The id of the train the team is on.
+    #[serde(default, deserialize_with = "deserialize_train_id")]
+    pub on_train: Option<u32>,
+    /// The remaining tickets of the team, `None` if the team can use trains without limit.
+    #[serde(default)]
+    pub tickets: Option<Tickets>,
+    #[serde(default)]
+    pub players: Vec<Player>,
+}
+
+impl TeamState {
+    pub fn position(&self) -> Point {
+        Point {
+            latitude: self.lat,
+            longitude: self.long,
+        }
+    }
 }
 
-#[derive(Default, Clone, Debug)]
+/// A member of a team. The position of a team is derived from the positions of its players.
+#[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Player {
     pub id: u32,
     pub name: String,
     pub team_id: u32,
+    pub long: f32,
+    pub lat: f32,
+    /// When the player last sent a position, `None` if the position is unknown.
+    pub located_at: Option<DateTime<Utc>>,
+    /// When the player last sent a message.
+    pub last_seen: Option<DateTime<Utc>>,
+    /// The number of connected devices of the player.
+    pub devices: u32,
+}
+
+impl Player {
+    pub fn position(&self) -> Option<Point> {
+        self.located_at.map(|_| Point {
+            latitude: self.lat,
+            longitude: self.long,
+        })
+    }
+
+    /// The player without its position, for teams whose position is secret.
+    pub fn without_position(&self) -> Self {
+        Self {
+            long: 0.,
+            lat: 0.,
+            located_at: None,
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(specta::Type, Clone, Deserialize, Debug)]
@@ -51,13 +394,129 @@ pub enum TeamKind {
     Observer,
 }
 
+/// The response to creating a team. Players need the join code to join the team.
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub struct CreatedTeam {
+    pub team: Team,
+    pub join_code: String,
+}
+
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub struct TeamJoinCode {
+    pub team_id: u32,
+    pub join_code: String,
+}
+
 #[derive(specta::Type, Clone, Serialize, Debug)]
 pub enum CreateTeamError {
     InvalidName,
+    /// Every game has exactly one Mr. X, which is created with the game.
+    MrXAlreadyExists,
     NameAlreadyExists,
+    GameAlreadyStarted,
+    GameNotFound,
 }
 
-#[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug)]
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub struct CreatedGame {
+    pub game: GameInfo,
+    pub mr_x_join_code: String,
+}
+
+/// A game room as listed by the API.
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub struct GameInfo {
+    pub id: u32,
+    pub name: String,
+    pub phase: GamePhase,
+    pub result: Option<GameResult>,
+    pub config: GameConfig,
+}
+
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct CreateGame {
+    pub name: String,
+    /// The config of the game, the server's default config is used if it is missing.
+    pub config: Option<GameConfig>,
+}
+
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct UpdateGame {
+    pub name: Option<String>,
+    /// The new config, which can only be changed before the game has started.
+    pub config: Option<GameConfig>,
+}
+
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub enum GameError {
+    NotFound,
+    TeamNotFound,
+    ClientNotFound,
+    NameAlreadyExists,
+    /// Every game needs a Mr. X team.
+    MrXRequired,
+    EmptyMessage,
+    /// The request needs a valid admin token.
+    Unauthorized,
+    InvalidName,
+    /// The request is not possible in the current phase of the game.
+    InvalidPhase {
+        phase: GamePhase,
+    },
+    /// No TRIAS API is configured.
+    TripPlanningUnavailable,
+    /// The TRIAS API couldn't plan the trip.
+    TripPlanningFailed {
+        message: String,
+    },
+}
+
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct UpdateTeam {
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+/// A position measured by the device of a player, with the optional fields of the browser's geolocation API.
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct PositionUpdate {
+    pub long: f32,
+    pub lat: f32,
+    /// The accuracy in meters.
+    #[serde(default)]
+    pub accuracy: Option<f32>,
+    /// The direction of travel in degrees clockwise from north.
+    #[serde(default)]
+    pub heading: Option<f32>,
+    /// The speed in m/s.
+    #[serde(default)]
+    pub speed: Option<f32>,
+    /// When the position was measured, the time it was received if missing.
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct TeamPosition {
+    pub long: f32,
+    pub lat: f32,
+}
+
+#[derive(specta::Type, Clone, Deserialize, Debug)]
+pub struct Announcement {
+    pub text: String,
+}
+
+/// A client connection as listed by the admin API.
+#[derive(specta::Type, Clone, Serialize, Debug)]
+pub struct ConnectionInfo {
+    pub id: u32,
+    pub team_id: u32,
+    pub player_id: Option<u32>,
+    pub connected: bool,
+}
+
+#[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Team {
     pub id: u32,
     pub name: String,
@@ -65,19 +524,131 @@ pub struct Team {
     pub kind: TeamKind,
 }
 
-#[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug)]
+/// A vehicle on its journey. The id stays the same while the server is running.
+#[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Train {
     pub id: u32,
     pub long: f32,
     pub lat: f32,
+    /// The TRIAS `LineRef`.
     pub line_id: String,
     pub line_name: String,
     pub direction: String,
+    pub mode: TransportMode,
+    /// The TRIAS `JourneyRef`, which is only unique on the operating day.
+    pub journey_ref: String,
+    pub operating_day: String,
+    /// The id of the stop the train is at, or arrives at next.
+    pub stop_id: String,
 }
 
-#[derive(Default, Clone, Debug)]
-pub struct Line {
-    pub id: u32,
-    pub name: String,
-    pub color: String,
+impl Train {
+    pub fn position(&self) -> Point {
+        Point {
+            latitude: self.lat,
+            longitude: self.long,
+        }
+    }
+}
+
+/// Reads a train id, ignoring the journey refs that older versions stored instead.
+fn deserialize_train_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TrainId {
+        Id(u32),
+        JourneyRef(IgnoredAny),
+    }
+    Ok(match Option::<TrainId>::deserialize(deserializer)? {
+        Some(TrainId::Id(id)) => Some(id),
+        Some(TrainId::JourneyRef(_)) | None => None,
+    })
+}
+
+#[derive(specta::Type, Default, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum TransportMode {
+    #[default]
+    Tram,
+    SBahn,
+    Bus,
+}
+
+#[derive(specta::Type, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Ticket {
+    Tram,
+    SBahn,
+    Bus,
+    /// Can be used for any mode of transport without revealing it.
+    Black,
+}
+
+impl From<TransportMode> for Ticket {
+    fn from(mode: TransportMode) -> Self {
+        match mode {
+            TransportMode::Tram => Self::Tram,
+            TransportMode::SBahn => Self::SBahn,
+            TransportMode::Bus => Self::Bus,
+        }
+    }
+}
+
+/// The number of tickets of each kind.
+#[derive(specta::Type, Default, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Tickets {
+    pub tram: u32,
+    pub s_bahn: u32,
+    pub bus: u32,
+    pub black: u32,
+}
+
+impl Tickets {
+    /// Use up a ticket, returns `false` if there is no ticket of this kind left.
+    pub fn take(&mut self, ticket: Ticket) -> bool {
+        let count = match ticket {
+            Ticket::Tram => &mut self.tram,
+            Ticket::SBahn => &mut self.s_bahn,
+            Ticket::Bus => &mut self.bus,
+            Ticket::Black => &mut self.black,
+        };
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+}
+
+/// A position update that implied an impossible speed.
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug)]
+pub struct PositionViolation {
+    pub time: DateTime<Utc>,
+    pub team_id: u32,
+    pub player_id: Option<u32>,
+    pub long: f32,
+    pub lat: f32,
+    pub speed_kmh: f32,
+    /// Whether the position was ignored.
+    pub rejected: bool,
+}
+
+#[derive(specta::Type, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct UsedTicket {
+    pub time: DateTime<Utc>,
+    pub ticket: Ticket,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id_of_invalid_message() {
+        let json = r#"{ "request_id": 7, "message": { "JoinTeam": { "team_id": "one" } } }"#;
+        assert!(serde_json::from_str::<ClientRequest>(json).is_err());
+        assert_eq!(ClientRequest::request_id_of(json), Some(7));
+
+        assert_eq!(ClientRequest::request_id_of(r#"{ "message": "Resync" }"#), None);
+        assert_eq!(ClientRequest::request_id_of(r#"{ "request_id": -1 }"#), None);
+        assert_eq!(ClientRequest::request_id_of(r#"{ "request_id": 7"#), None);
+    }
 }
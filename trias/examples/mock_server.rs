@@ -0,0 +1,29 @@
+//! Run the mock TRIAS server with the fixtures, e.g. to point robusta at it with `TRIAS_API_ENDPOINT`. The times of the
+//! fixtures are moved to the time of each request, so robusta always shows a train between the fixture stops:
+//!
+//! ```sh
+//! cargo run -p trias --features mock --example mock_server -- 127.0.0.1:8090
+//! ```
+
+use std::net::SocketAddr;
+
+use trias::mock::MockServer;
+
+#[tokio::main]
+async fn main() {
+    let addr: SocketAddr = std::env::args()
+        .nth(1)
+        .map(|addr| addr.parse().expect("invalid address"))
+        .unwrap_or_else(|| ([127, 0, 0, 1], 8090).into());
+
+    let server = MockServer::builder()
+        .addr(addr)
+        .fixtures()
+        .relative_times()
+        .start()
+        .await
+        .expect("failed to start the mock server");
+    println!("TRIAS_API_ENDPOINT={}", server.endpoint());
+
+    tokio::signal::ctrl_c().await.expect("failed to wait for Ctrl-C");
+}
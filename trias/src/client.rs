@@ -0,0 +1,227 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::location_information::Circle;
+use crate::location_information::Location;
+use crate::response::{DeliveryPayload, GeoPosition, TriasResponse};
+use crate::stop_event::{StopEventParams, StopEventResponse};
+use crate::trip::{TripResponse, TripResult};
+use crate::trip_info::{TripInfoParams, TripInfoRequest, TripInfoResult};
+use crate::{
+    generate_service_request, Error, GeoRestriction, LocationInformationRequestBuilder, RequestPayload,
+    StopEventRequestBuilder,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_USER_AGENT: &str = concat!("trias/", env!("CARGO_PKG_VERSION"));
+
+/// A client for a TRIAS API. It reuses its connections, so it should be created once and cloned where needed.
+#[derive(Clone)]
+pub struct TriasClient {
+    endpoint: String,
+    requestor_ref: String,
+    http: reqwest::Client,
+}
+
+impl TriasClient {
+    /// A client with the default settings, `requestor_ref` is the access token.
+    pub fn new(endpoint: String, requestor_ref: String) -> Result<Self, Error> {
+        TriasClientBuilder::new(endpoint, requestor_ref).build()
+    }
+
+    pub fn builder(endpoint: String, requestor_ref: String) -> TriasClientBuilder {
+        TriasClientBuilder::new(endpoint, requestor_ref)
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Send a request and return the payload of the response.
+    pub async fn request(&self, payload: RequestPayload) -> Result<DeliveryPayload, Error> {
+        let request = generate_service_request(self.requestor_ref.clone(), payload)?;
+        Ok(self.send(request).await?.service_delivery.delivery_payload)
+    }
+
+    /// Send a request that is already serialized, see [`generate_service_request`].
+    pub(crate) async fn send(&self, request: String) -> Result<TriasResponse, Error> {
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .header(reqwest::header::CONTENT_TYPE, "application/xml")
+            .body(request)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::Status(response.status()));
+        }
+        let response = response.text().await?;
+
+        serde_xml_rs::from_str(&response).map_err(Error::Deserialize)
+    }
+
+    /// Search stops by name.
+    pub async fn search_stops(&self, name: String, number_of_results: u32) -> Result<Vec<Location>, Error> {
+        let payload = LocationInformationRequestBuilder::new(name)
+            .number_of_results(number_of_results)
+            .include_pt_modes(false)
+            .build();
+        self.locations(payload).await
+    }
+
+    /// Search the stops within `radius` meters of a position.
+    pub async fn stops_near(
+        &self,
+        position: GeoPosition,
+        radius: u32,
+        number_of_results: u32,
+    ) -> Result<Vec<Location>, Error> {
+        let restriction = GeoRestriction::Circle(Circle {
+            center: position,
+            radius,
+        });
+        let payload = LocationInformationRequestBuilder::within(restriction)
+            .number_of_results(number_of_results)
+            .build();
+        self.locations(payload).await
+    }
+
+    /// Search locations, built with a [`LocationInformationRequestBuilder`].
+    pub async fn locations(&self, payload: RequestPayload) -> Result<Vec<Location>, Error> {
+        let DeliveryPayload::LocationInformationResponse(response) = self.request(payload).await? else {
+            return Err(Error::UnexpectedPayload);
+        };
+        match response.error_message {
+            Some(err) if response.location_result.is_empty() => Err(err.into()),
+            _ => Ok(response.location_result.into_iter().map(|x| x.location).collect()),
+        }
+    }
+
+    /// The next departures at a stop, with the previous and onward calls of their journeys.
+    pub async fn stop_events(&self, location_ref: String, number_of_results: u32) -> Result<StopEventResponse, Error> {
+        let params = StopEventParams {
+            number_of_results,
+            include_realtime_data: true,
+            include_previous_calls: true,
+            include_onward_calls: true,
+            ..Default::default()
+        };
+        let payload = StopEventRequestBuilder::new(location_ref).params(params).build();
+
+        let DeliveryPayload::StopEventResponse(response) = self.request(payload).await? else {
+            return Err(Error::UnexpectedPayload);
+        };
+        match response.error_message {
+            Some(err) if response.stop_event_result.is_empty() => Err(err.into()),
+            _ => Ok(response),
+        }
+    }
+
+    /// The calls and the position of a journey.
+    pub async fn trip_info(&self, journey_ref: String, operating_day_ref: String) -> Result<TripInfoResult, Error> {
+        let payload = RequestPayload::TripInfoRequest(TripInfoRequest {
+            journey_ref,
+            operating_day_ref,
+            params: TripInfoParams::default(),
+        });
+
+        let DeliveryPayload::TripInfoResponse(response) = self.request(payload).await? else {
+            return Err(Error::UnexpectedPayload);
+        };
+        match (response.trip_info_result, response.error_message) {
+            (Some(result), _) => Ok(result),
+            (None, Some(err)) => Err(err.into()),
+            (None, None) => Err(Error::UnexpectedPayload),
+        }
+    }
+
+    /// Plan trips, built with a [`TripRequestBuilder`](crate::TripRequestBuilder).
+    pub async fn trips(&self, payload: RequestPayload) -> Result<Vec<TripResult>, Error> {
+        let DeliveryPayload::TripResponse(response) = self.request(payload).await? else {
+            return Err(Error::UnexpectedPayload);
+        };
+        let TripResponse {
+            error_message,
+            trip_result,
+        } = response;
+        match error_message {
+            Some(err) if trip_result.is_empty() => Err(err.into()),
+            _ => Ok(trip_result),
+        }
+    }
+}
+
+impl fmt::Debug for TriasClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the requestor ref is the access token, it must not end up in logs
+        f.debug_struct("TriasClient")
+            .field("endpoint", &self.endpoint)
+            .field("requestor_ref", &"<redacted>")
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct TriasClientBuilder {
+    endpoint: String,
+    requestor_ref: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+    language: Option<String>,
+}
+
+impl TriasClientBuilder {
+    pub fn new(endpoint: String, requestor_ref: String) -> Self {
+        TriasClientBuilder {
+            endpoint,
+            requestor_ref,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: None,
+            user_agent: DEFAULT_USER_AGENT.to_owned(),
+            language: None,
+        }
+    }
+
+    /// The timeout of a whole request, including reading the response.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_owned();
+        self
+    }
+
+    /// The preferred language of the texts in the responses, e.g. `de`, sent as `Accept-Language`.
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_owned());
+        self
+    }
+
+    pub fn build(self) -> Result<TriasClient, Error> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(language) = self.language {
+            let language = language.parse().map_err(|_| Error::InvalidConfig("language"))?;
+            headers.insert(reqwest::header::ACCEPT_LANGUAGE, language);
+        }
+        let mut http = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .user_agent(self.user_agent)
+            .default_headers(headers);
+        if let Some(connect_timeout) = self.connect_timeout {
+            http = http.connect_timeout(connect_timeout);
+        }
+        Ok(TriasClient {
+            endpoint: self.endpoint,
+            requestor_ref: self.requestor_ref,
+            // the builder only fails because of its settings, e.g. an invalid user agent
+            http: http.build().map_err(|_| Error::InvalidConfig("HTTP client settings"))?,
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+use crate::response::ErrorMessage;
+
+/// The errors of TRIAS requests.
+#[derive(Debug)]
+pub enum Error {
+    /// A setting of the client is invalid, e.g. a language that can't be sent as a header.
+    InvalidConfig(&'static str),
+    /// The request couldn't be serialized to XML.
+    Serialize(serde_xml_rs::Error),
+    /// The request couldn't be sent or the response couldn't be received, e.g. because the server is unreachable.
+    Transport(reqwest::Error),
+    /// The server answered with an error status.
+    Status(StatusCode),
+    /// The response isn't valid TRIAS XML.
+    Deserialize(serde_xml_rs::Error),
+    /// The server reported an error, e.g. `STOPEVENT_LOCATIONUNSERVED` for a stop without departures.
+    Trias { code: String, message: String },
+    /// The response has a different payload than the request asked for.
+    UnexpectedPayload,
+}
+
+impl Error {
+    /// Whether the request may succeed when it is sent again later.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Transport(_) => true,
+            Self::Status(status) => status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig(setting) => write!(f, "invalid {}", setting),
+            Self::Serialize(err) => write!(f, "failed to serialize the request: {}", err),
+            Self::Transport(err) => write!(f, "failed to send the request: {}", err),
+            Self::Status(status) => write!(f, "the server answered with {}", status),
+            Self::Deserialize(err) => write!(f, "failed to deserialize the response: {}", err),
+            Self::Trias { code, message } => write!(f, "TRIAS error {}: {}", code, message),
+            Self::UnexpectedPayload => write!(f, "unexpected response payload"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Serialize(err) | Self::Deserialize(err) => Some(err),
+            Self::Transport(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Transport(err)
+    }
+}
+
+impl From<ErrorMessage> for Error {
+    fn from(err: ErrorMessage) -> Self {
+        Self::Trias {
+            code: err.code,
+            message: err.text.text,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::response::{ErrorMessage, GeoPosition, Text};
+use crate::trip::PtModeFilter;
 use crate::RequestPayload;
 
 // request
@@ -15,7 +16,36 @@ pub struct LocationInformationRequest {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct InitialInput {
-    pub location_name: String,
+    /// Search by name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_name: Option<String>,
+    /// Search the locations near a position.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo_position: Option<GeoPosition>,
+    /// Search the locations in an area.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo_restriction: Option<GeoRestriction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum GeoRestriction {
+    Circle(Circle),
+    Rectangle(Rectangle),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Circle {
+    pub center: GeoPosition,
+    /// The radius in meters.
+    pub radius: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Rectangle {
+    pub upper_left: GeoPosition,
+    pub lower_right: GeoPosition,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,37 +53,103 @@ pub struct InitialInput {
 pub struct Restrictions {
     #[serde(rename = "Type")]
     pub location_type: String, // Using location_type to avoid Rust's reserved word 'type'
+    /// Only stops served by these modes of transport, or not served by them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pt_modes: Option<PtModeFilter>,
+    /// Only locations in these localities.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locality_ref: Vec<String>,
     pub number_of_results: u32,
     pub include_pt_modes: bool,
 }
 
 pub struct LocationInformationRequestBuilder {
-    location_name: String,
+    initial_input: InitialInput,
     location_type: String,
+    pt_modes: Option<PtModeFilter>,
+    locality_refs: Vec<String>,
     number_of_results: u32,
     include_pt_modes: bool,
 }
 
 impl LocationInformationRequestBuilder {
     pub fn new(location_name: String) -> Self {
+        Self::with_input(InitialInput {
+            location_name: Some(location_name),
+            geo_position: None,
+            geo_restriction: None,
+        })
+    }
+
+    /// Search the locations nearest to a position.
+    pub fn near(position: GeoPosition) -> Self {
+        Self::with_input(InitialInput {
+            location_name: None,
+            geo_position: Some(position),
+            geo_restriction: None,
+        })
+    }
+
+    /// Search the locations in an area.
+    pub fn within(restriction: GeoRestriction) -> Self {
+        Self::with_input(InitialInput {
+            location_name: None,
+            geo_position: None,
+            geo_restriction: Some(restriction),
+        })
+    }
+
+    fn with_input(initial_input: InitialInput) -> Self {
         LocationInformationRequestBuilder {
-            location_name,
+            initial_input,
             location_type: "stop".to_owned(),
+            pt_modes: None,
+            locality_refs: Vec::new(),
             number_of_results: 2,
             include_pt_modes: false,
         }
     }
 
     pub fn location_name(mut self, location_name: String) -> Self {
-        self.location_name = location_name;
+        self.initial_input.location_name = Some(location_name);
         self
     }
 
+    pub fn geo_restriction(mut self, restriction: GeoRestriction) -> Self {
+        self.initial_input.geo_restriction = Some(restriction);
+        self
+    }
+
+    /// The kind of locations to search, e.g. `stop`, `address`, `poi` or `locality`.
     pub fn location_type(mut self, location_type: &str) -> Self {
         self.location_type = location_type.to_string();
         self
     }
 
+    /// Only search stops served by these modes of transport.
+    pub fn include_modes(mut self, modes: Vec<String>) -> Self {
+        self.pt_modes = Some(PtModeFilter {
+            exclude: false,
+            pt_mode: modes,
+        });
+        self
+    }
+
+    /// Only search stops not served by these modes of transport.
+    pub fn exclude_modes(mut self, modes: Vec<String>) -> Self {
+        self.pt_modes = Some(PtModeFilter {
+            exclude: true,
+            pt_mode: modes,
+        });
+        self
+    }
+
+    /// Only search locations in the locality, can be given multiple times.
+    pub fn locality(mut self, locality_ref: String) -> Self {
+        self.locality_refs.push(locality_ref);
+        self
+    }
+
     pub fn number_of_results(mut self, number_of_results: u32) -> Self {
         self.number_of_results = number_of_results;
         self
@@ -66,11 +162,11 @@ impl LocationInformationRequestBuilder {
 
     pub fn build(self) -> RequestPayload {
         RequestPayload::LocationInformationRequest(LocationInformationRequest {
-            initial_input: InitialInput {
-                location_name: self.location_name,
-            },
+            initial_input: self.initial_input,
             restrictions: Restrictions {
                 location_type: self.location_type,
+                pt_modes: self.pt_modes,
+                locality_ref: self.locality_refs,
                 number_of_results: self.number_of_results,
                 include_pt_modes: self.include_pt_modes,
             },
@@ -96,10 +192,14 @@ pub struct LocationResult {
     pub probability: f64,
 }
 
+/// A location found by a search, exactly one of its kinds is set.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct Location {
-    pub stop_point: StopPoint,
+    pub stop_point: Option<StopPoint>,
+    pub address: Option<Address>,
+    pub point_of_interest: Option<PointOfInterest>,
+    pub locality: Option<Locality>,
     pub location_name: Text,
     pub geo_position: GeoPosition,
 }
@@ -114,3 +214,30 @@ pub struct StopPoint {
     pub lighting: bool,
     pub covered: bool,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Address {
+    pub address_code: String,
+    pub address_name: Text,
+    pub postal_code: Option<String>,
+    pub locality_name: Option<Text>,
+    pub locality_ref: Option<String>,
+    pub street_name: Option<String>,
+    pub house_number: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PointOfInterest {
+    pub point_of_interest_code: String,
+    pub point_of_interest_name: Text,
+    pub locality_ref: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Locality {
+    pub locality_code: String,
+    pub locality_name: Text,
+}
@@ -0,0 +1,311 @@
+//! A mock TRIAS server for tests, answering requests with fixed responses instead of asking a real TRIAS API.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use trias::mock::{fixtures, MockServer, RequestKind};
+//!
+//! let server = MockServer::builder()
+//!     .respond(RequestKind::StopEvent, fixtures::STOP_EVENT_ERROR)
+//!     .start()
+//!     .await?;
+//! let client = trias::TriasClient::new(server.endpoint(), "token".to_owned())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use tokio::sync::oneshot;
+
+/// Responses of the KVV TRIAS API, see `tests/fixtures`.
+pub mod fixtures {
+    /// A search for `Marktplatz`, with the stops `de:08212:1003` and `de:08212:1011`.
+    pub const LOCATION_INFORMATION: &str = include_str!("../tests/fixtures/location_information.xml");
+    /// A search without results, `LOCATION_NORESULTS`.
+    pub const LOCATION_INFORMATION_ERROR: &str = include_str!("../tests/fixtures/location_information_error.xml");
+    /// Two departures at `de:08212:1003` with their previous and onward calls.
+    pub const STOP_EVENT: &str = include_str!("../tests/fixtures/stop_event.xml");
+    /// A stop without departures, `STOPEVENT_LOCATIONUNSERVED`.
+    pub const STOP_EVENT_ERROR: &str = include_str!("../tests/fixtures/stop_event_error.xml");
+    /// The calls and the position of the journey `kvv:21002:E:H:j23:1234`.
+    pub const TRIP_INFO: &str = include_str!("../tests/fixtures/trip_info.xml");
+    /// An unknown journey, `TRIPINFO_JOURNEYREFUNKNOWN`.
+    pub const TRIP_INFO_ERROR: &str = include_str!("../tests/fixtures/trip_info_error.xml");
+    /// A trip from `de:08212:7` by tram and on foot.
+    pub const TRIP: &str = include_str!("../tests/fixtures/trip.xml");
+
+    /// When the responses were recorded, see [`MockServerBuilder::relative_times`].
+    pub const RECORDED_AT: &str = "2023-10-11T11:24:29Z";
+
+    /// The stops the journey in [`STOP_EVENT`] calls at, as `(id, name, longitude, latitude)`.
+    pub const STOPS: &[(&str, &str, f64, f64)] = &[
+        ("de:08212:1004", "Europaplatz/Postgalerie (U)", 8.39558, 49.00979),
+        ("de:08212:1003", "Marktplatz (Kaiserstraße U)", 8.40377, 49.00947),
+        ("de:08212:1002", "Kronenplatz (U)", 8.40861, 49.00925),
+        ("de:08212:1001", "Durlacher Tor/KIT-Campus Süd (U)", 8.41668, 49.00933),
+    ];
+
+    /// A search for a stop id, with the stop as the only result.
+    pub fn location_information(id: &str, name: &str, longitude: f64, latitude: f64) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<trias:Trias xmlns:siri="http://www.siri.org.uk/siri" xmlns:trias="http://www.vdv.de/trias" version="1.2">
+  <trias:ServiceDelivery>
+    <siri:ResponseTimestamp>{RECORDED_AT}</siri:ResponseTimestamp>
+    <siri:ProducerRef>mock</siri:ProducerRef>
+    <siri:Status>true</siri:Status>
+    <trias:MoreData>false</trias:MoreData>
+    <trias:Language>de</trias:Language>
+    <trias:DeliveryPayload>
+      <trias:LocationInformationResponse>
+        <trias:LocationResult>
+          <trias:Location>
+            <trias:StopPoint>
+              <siri:StopPointRef>{id}</siri:StopPointRef>
+              <trias:StopPointName>
+                <trias:Text>{name}</trias:Text>
+                <trias:Language>de</trias:Language>
+              </trias:StopPointName>
+              <trias:LocalityRef>8212000:15</trias:LocalityRef>
+              <trias:WheelchairAccessible>false</trias:WheelchairAccessible>
+              <trias:Lighting>false</trias:Lighting>
+              <trias:Covered>false</trias:Covered>
+            </trias:StopPoint>
+            <trias:LocationName>
+              <trias:Text>Karlsruhe</trias:Text>
+              <trias:Language>de</trias:Language>
+            </trias:LocationName>
+            <trias:GeoPosition>
+              <trias:Longitude>{longitude}</trias:Longitude>
+              <trias:Latitude>{latitude}</trias:Latitude>
+            </trias:GeoPosition>
+          </trias:Location>
+          <trias:Complete>true</trias:Complete>
+          <trias:Probability>1</trias:Probability>
+        </trias:LocationResult>
+      </trias:LocationInformationResponse>
+    </trias:DeliveryPayload>
+  </trias:ServiceDelivery>
+</trias:Trias>"#
+        )
+    }
+}
+
+/// The kind of a request, by its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    LocationInformation,
+    StopEvent,
+    TripInfo,
+    Trip,
+}
+
+impl RequestKind {
+    fn of(request: &str) -> Option<Self> {
+        // the payload elements may be prefixed with a namespace, e.g. `trias:StopEventRequest`
+        [
+            ("LocationInformationRequest>", Self::LocationInformation),
+            ("StopEventRequest>", Self::StopEvent),
+            ("TripInfoRequest>", Self::TripInfo),
+            ("TripRequest>", Self::Trip),
+        ]
+        .into_iter()
+        .find(|(element, _)| request.contains(element))
+        .map(|(_, kind)| kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    kind: RequestKind,
+    /// Only answer requests containing this text, e.g. a stop id.
+    matching: Option<String>,
+    status: StatusCode,
+    body: String,
+}
+
+/// A running mock server, it is shut down when it is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockServer {
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// A server answering with the [`fixtures`]: the [`fixtures::STOPS`] and `Marktplatz` can be found, every stop
+    /// has the same departures and every journey the same calls.
+    pub async fn with_fixtures() -> Result<Self, hyper::Error> {
+        Self::builder().fixtures().start().await
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The URL to send requests to, e.g. as `TRIAS_API_ENDPOINT`.
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/trias", self.addr)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MockServerBuilder {
+    addr: Option<SocketAddr>,
+    routes: Vec<Route>,
+    relative_times: bool,
+}
+
+impl MockServerBuilder {
+    /// Listen on this address instead of a free port on localhost.
+    pub fn addr(mut self, addr: SocketAddr) -> Self {
+        self.addr = Some(addr);
+        self
+    }
+
+    /// Answer requests of this kind with `body`. The first response given for a request is used.
+    pub fn respond(self, kind: RequestKind, body: &str) -> Self {
+        self.route(kind, None, StatusCode::OK, body)
+    }
+
+    /// Answer requests of this kind containing `text`, e.g. a stop id, with `body`.
+    pub fn respond_matching(self, kind: RequestKind, text: &str, body: &str) -> Self {
+        self.route(kind, Some(text.to_owned()), StatusCode::OK, body)
+    }
+
+    /// Answer requests of this kind with an error status and an empty body.
+    pub fn fail(self, kind: RequestKind, status: StatusCode) -> Self {
+        self.route(kind, None, status, "")
+    }
+
+    /// Move the times in the responses as if they were recorded at the time of the request instead of at
+    /// [`fixtures::RECORDED_AT`], so the departures of the fixtures are always about to happen.
+    pub fn relative_times(mut self) -> Self {
+        self.relative_times = true;
+        self
+    }
+
+    /// Answer with the [`fixtures`], after the responses given so far.
+    pub fn fixtures(mut self) -> Self {
+        for &(id, name, longitude, latitude) in fixtures::STOPS {
+            let body = fixtures::location_information(id, name, longitude, latitude);
+            // the delimiters keep `de:08212:1` from matching `de:08212:1003`
+            self = self.respond_matching(RequestKind::LocationInformation, &format!(">{}<", id), &body);
+        }
+        self.respond_matching(
+            RequestKind::LocationInformation,
+            "Marktplatz",
+            fixtures::LOCATION_INFORMATION,
+        )
+        .respond(RequestKind::LocationInformation, fixtures::LOCATION_INFORMATION_ERROR)
+        .respond(RequestKind::StopEvent, fixtures::STOP_EVENT)
+        .respond(RequestKind::TripInfo, fixtures::TRIP_INFO)
+        .respond(RequestKind::Trip, fixtures::TRIP)
+    }
+
+    fn route(mut self, kind: RequestKind, matching: Option<String>, status: StatusCode, body: &str) -> Self {
+        self.routes.push(Route {
+            kind,
+            matching,
+            status,
+            body: body.to_owned(),
+        });
+        self
+    }
+
+    /// Start the server on the current tokio runtime.
+    pub async fn start(self) -> Result<MockServer, hyper::Error> {
+        let addr = self.addr.unwrap_or_else(|| ([127, 0, 0, 1], 0).into());
+        let responses = Arc::new(Responses {
+            routes: self.routes,
+            relative_times: self.relative_times,
+        });
+        let make_service = make_service_fn(move |_| {
+            let responses = responses.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |request| answer(responses.clone(), request))) }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_service);
+        let addr = server.local_addr();
+
+        let (shutdown, shutdown_received) = oneshot::channel();
+        let server = server.with_graceful_shutdown(async {
+            shutdown_received.await.ok();
+        });
+        tokio::spawn(server);
+        Ok(MockServer {
+            addr,
+            _shutdown: shutdown,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Responses {
+    routes: Vec<Route>,
+    relative_times: bool,
+}
+
+async fn answer(responses: Arc<Responses>, request: Request<Body>) -> Result<Response<Body>, hyper::Error> {
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+    let body = String::from_utf8_lossy(&body);
+    let Some(kind) = RequestKind::of(&body) else {
+        return Ok(status(StatusCode::BAD_REQUEST));
+    };
+    let route = responses
+        .routes
+        .iter()
+        .find(|route| route.kind == kind && route.matching.as_ref().is_none_or(|text| body.contains(text.as_str())));
+    let Some(route) = route else {
+        return Ok(status(StatusCode::NOT_IMPLEMENTED));
+    };
+    let body = if responses.relative_times {
+        let recorded_at = fixtures::RECORDED_AT.parse().expect("valid time");
+        shift_times(&route.body, recorded_at, Utc::now())
+    } else {
+        route.body.clone()
+    };
+    let response = Response::builder()
+        .status(route.status)
+        .header(hyper::header::CONTENT_TYPE, "application/xml")
+        .body(Body::from(body))
+        .expect("the response is valid");
+    Ok(response)
+}
+
+/// Move the times and dates in the text of the elements as if they were recorded at `to` instead of `from`.
+fn shift_times(xml: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    let offset = to - from;
+    let days = to.date_naive() - from.date_naive();
+    let mut shifted = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find('>') {
+        let (before, after) = rest.split_at(start + 1);
+        shifted.push_str(before);
+        let end = after.find('<').unwrap_or(after.len());
+        let (text, after) = after.split_at(end);
+        if let Ok(time) = text.parse::<DateTime<Utc>>() {
+            shifted.push_str(&(time + offset).to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+        } else if let Ok(date) = text.parse::<NaiveDate>() {
+            shifted.push_str(&(date + days).to_string());
+        } else {
+            shifted.push_str(text);
+        }
+        rest = after;
+    }
+    shifted.push_str(rest);
+    shifted
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::location_information::LocationInformationResponse;
 use crate::stop_event::StopEventResponse;
+use crate::trip::TripResponse;
 use crate::trip_info::TripInfoResponse;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -27,7 +28,8 @@ pub struct ServiceDelivery {
 pub enum DeliveryPayload {
     LocationInformationResponse(LocationInformationResponse),
     StopEventResponse(StopEventResponse),
-    TripInfoResponse(TripInfoResponse),
+    TripInfoResponse(Box<TripInfoResponse>),
+    TripResponse(TripResponse),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -48,6 +50,8 @@ pub struct Text {
 pub enum Language {
     #[serde(rename = "de")]
     De,
+    #[serde(rename = "en")]
+    En,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -103,11 +107,11 @@ pub struct ServiceSection {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct Mode {
-    pt_mode: String,
-    tram_submode: Option<String>,
-    name: Text,
-    rail_submode: Option<String>,
-    bus_submode: Option<String>,
+    pub pt_mode: String,
+    pub tram_submode: Option<String>,
+    pub name: Text,
+    pub rail_submode: Option<String>,
+    pub bus_submode: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -0,0 +1,283 @@
+use chrono::{DateTime, Utc};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::response::{ErrorMessage, GeoPosition, Service, ServiceSection, Text};
+use crate::RequestPayload;
+
+// request
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TripRequest {
+    pub origin: LocationContext,
+    pub destination: LocationContext,
+    #[serde(default)]
+    pub via: Vec<Via>,
+    pub params: TripParams,
+}
+
+// serde-xml-rs can't serialize sequences of structs, so the via points are written as repeated fields
+impl Serialize for TripRequest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut request = serializer.serialize_struct("TripRequest", 3 + self.via.len())?;
+        request.serialize_field("Origin", &self.origin)?;
+        request.serialize_field("Destination", &self.destination)?;
+        for via in &self.via {
+            request.serialize_field("Via", via)?;
+        }
+        request.serialize_field("Params", &self.params)?;
+        request.end()
+    }
+}
+
+/// A location of a trip, the time is the departure time at the origin or the arrival time at the destination.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct LocationContext {
+    pub location_ref: TripLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dep_arr_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum TripLocation {
+    #[serde(rename = "StopPointRef")]
+    Stop(String),
+    #[serde(rename = "GeoPosition")]
+    Position(GeoPosition),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Via {
+    pub via_point: TripLocation,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TripParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pt_mode_filter: Option<PtModeFilter>,
+    pub number_of_results: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interchange_limit: Option<u32>,
+    pub include_track_sections: bool,
+    pub include_leg_projection: bool,
+    pub include_intermediate_stops: bool,
+}
+
+impl Default for TripParams {
+    fn default() -> Self {
+        Self {
+            pt_mode_filter: None,
+            number_of_results: 3,
+            interchange_limit: None,
+            include_track_sections: false,
+            include_leg_projection: false,
+            include_intermediate_stops: false,
+        }
+    }
+}
+
+/// Restricts the modes of transport of a trip, e.g. `bus` or `tram`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PtModeFilter {
+    /// Whether the modes are excluded, otherwise only they are used.
+    pub exclude: bool,
+    pub pt_mode: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TripRequestBuilder {
+    origin: TripLocation,
+    destination: TripLocation,
+    departure_time: Option<DateTime<Utc>>,
+    arrival_time: Option<DateTime<Utc>>,
+    via: Vec<TripLocation>,
+    params: TripParams,
+}
+
+impl TripRequestBuilder {
+    /// A request for trips departing now.
+    pub fn new(origin: TripLocation, destination: TripLocation) -> Self {
+        Self {
+            origin,
+            destination,
+            departure_time: Some(Utc::now()),
+            arrival_time: None,
+            via: Vec::new(),
+            params: TripParams::default(),
+        }
+    }
+
+    pub fn departure_time(mut self, departure_time: DateTime<Utc>) -> Self {
+        self.departure_time = Some(departure_time);
+        self.arrival_time = None;
+        self
+    }
+
+    /// Plan trips arriving until `arrival_time` instead of departing at a time.
+    pub fn arrival_time(mut self, arrival_time: DateTime<Utc>) -> Self {
+        self.arrival_time = Some(arrival_time);
+        self.departure_time = None;
+        self
+    }
+
+    pub fn via(mut self, via: TripLocation) -> Self {
+        self.via.push(via);
+        self
+    }
+
+    /// Only use these modes of transport.
+    pub fn include_modes(mut self, modes: Vec<String>) -> Self {
+        self.params.pt_mode_filter = Some(PtModeFilter {
+            exclude: false,
+            pt_mode: modes,
+        });
+        self
+    }
+
+    /// Don't use these modes of transport.
+    pub fn exclude_modes(mut self, modes: Vec<String>) -> Self {
+        self.params.pt_mode_filter = Some(PtModeFilter {
+            exclude: true,
+            pt_mode: modes,
+        });
+        self
+    }
+
+    pub fn interchange_limit(mut self, interchange_limit: u32) -> Self {
+        self.params.interchange_limit = Some(interchange_limit);
+        self
+    }
+
+    pub fn number_of_results(mut self, number_of_results: u32) -> Self {
+        self.params.number_of_results = number_of_results;
+        self
+    }
+
+    pub fn include_intermediate_stops(mut self, include_intermediate_stops: bool) -> Self {
+        self.params.include_intermediate_stops = include_intermediate_stops;
+        self
+    }
+
+    pub fn build(self) -> RequestPayload {
+        RequestPayload::TripRequest(TripRequest {
+            origin: LocationContext {
+                location_ref: self.origin,
+                dep_arr_time: self.departure_time,
+            },
+            destination: LocationContext {
+                location_ref: self.destination,
+                dep_arr_time: self.arrival_time,
+            },
+            via: self.via.into_iter().map(|via_point| Via { via_point }).collect(),
+            params: self.params,
+        })
+    }
+}
+
+// response
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TripResponse {
+    pub error_message: Option<ErrorMessage>,
+    #[serde(default)]
+    pub trip_result: Vec<TripResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TripResult {
+    pub result_id: String,
+    pub trip: Trip,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Trip {
+    pub trip_id: String,
+    /// The duration as ISO 8601 duration, e.g. `PT27M`.
+    pub duration: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    #[serde(default)]
+    pub interchanges: u32,
+    /// The length in meters.
+    pub distance: Option<u32>,
+    #[serde(default)]
+    pub trip_leg: Vec<TripLeg>,
+}
+
+/// A part of a trip, exactly one of the legs is set.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TripLeg {
+    pub leg_id: String,
+    /// A ride with a public transport service.
+    pub timed_leg: Option<TimedLeg>,
+    /// A walk between two services.
+    pub interchange_leg: Option<InterchangeLeg>,
+    /// A walk from the origin or to the destination.
+    pub continuous_leg: Option<InterchangeLeg>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TimedLeg {
+    pub leg_board: LegCall,
+    #[serde(default)]
+    pub leg_intermediates: Vec<LegCall>,
+    pub leg_alight: LegCall,
+    pub service: LegService,
+}
+
+/// A stop of a timed leg.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct LegCall {
+    pub stop_point_ref: String,
+    pub stop_point_name: Text,
+    pub planned_bay: Option<Text>,
+    pub service_arrival: Option<Service>,
+    pub service_departure: Option<Service>,
+    pub stop_seq_number: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct LegService {
+    pub operating_day_ref: String,
+    pub journey_ref: String,
+    pub service_section: ServiceSection,
+    pub destination_text: Option<Text>,
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct InterchangeLeg {
+    /// e.g. `walk`
+    #[serde(alias = "ContinuousMode")]
+    pub interchange_mode: Option<String>,
+    pub leg_start: LegLocation,
+    pub leg_end: LegLocation,
+    pub time_window_start: DateTime<Utc>,
+    pub time_window_end: DateTime<Utc>,
+    /// The duration as ISO 8601 duration, e.g. `PT5M`.
+    pub duration: String,
+    /// The length in meters.
+    pub length: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct LegLocation {
+    pub stop_point_ref: Option<String>,
+    pub geo_position: Option<GeoPosition>,
+    pub location_name: Text,
+}
@@ -1,50 +1,214 @@
-use trias::response::DeliveryPayload;
+use chrono::{Duration, Utc};
+use reqwest::StatusCode;
+use trias::mock::{fixtures, MockServer, RequestKind};
+use trias::{Error, LocationInformationRequestBuilder, TriasClient, TripLocation, TripRequestBuilder};
+
+fn client(server: &MockServer) -> TriasClient {
+    TriasClient::new(server.endpoint(), "API-Explorer".to_owned()).unwrap()
+}
 
 #[tokio::test]
-async fn test_fetch_location_information() {
-    dotenv::dotenv().ok();
-    let api_endpoint = "https://projekte.kvv-efa.de/koberttrias/trias"; // Replace with your API endpoint
-    let access_token = std::env::var("TRIAS_ACCESS_TOKEN").expect("TRIAS_ACCESS_TOKEN not set");
+async fn test_search_stops() {
+    let server = MockServer::with_fixtures().await.unwrap();
+    let client = client(&server);
 
-    let payload = trias::LocationInformationRequestBuilder::new("Karlsruhe Hauptbahnhof".to_owned())
-        .number_of_results(2)
-        .include_pt_modes(false)
-        .build()
-        //... set other fields ...
-        ;
+    let stops = client.search_stops("de:08212:1003".to_owned(), 1).await.unwrap();
+    assert_eq!(stops[0].stop_point.as_ref().unwrap().stop_point_ref, "de:08212:1003");
 
-    let xml_request = trias::generate_service_request(access_token, payload).unwrap();
-    let response = trias::post_request(api_endpoint, &xml_request).await.unwrap();
+    let payload = LocationInformationRequestBuilder::new("Marktplatz".to_owned()).build();
+    assert_eq!(client.locations(payload).await.unwrap().len(), 2);
 
-    let DeliveryPayload::LocationInformationResponse(response) = response.service_delivery.delivery_payload else {
-        panic!("Wrong response type");
-    };
-    for stop in &response.location_result {
-        println!("{:?}", stop);
+    let err = client.search_stops("Nowhere".to_owned(), 1).await.unwrap_err();
+    assert!(matches!(&err, Error::Trias { code, .. } if code == "LOCATION_NORESULTS"));
+}
+
+#[tokio::test]
+async fn test_fixture_stops() {
+    let server = MockServer::with_fixtures().await.unwrap();
+    let client = client(&server);
+
+    for &(id, name, longitude, latitude) in fixtures::STOPS {
+        let stops = client.search_stops(id.to_owned(), 1).await.unwrap();
+        let stop_point = stops[0].stop_point.as_ref().unwrap();
+        assert_eq!(stop_point.stop_point_ref, id);
+        assert_eq!(stop_point.stop_point_name.text, name);
+        assert_eq!(stops[0].geo_position.longitude, longitude);
+        assert_eq!(stops[0].geo_position.latitude, latitude);
     }
-    assert!(response.location_result.len() > 0);
+    assert!(client.search_stops("de:08212:1".to_owned(), 1).await.is_err());
 }
 
 #[tokio::test]
-async fn test_fetch_stop_event() {
-    dotenv::dotenv().ok();
-    let api_endpoint = "https://projekte.kvv-efa.de/koberttrias/trias"; // Replace with your API endpoint
-    let access_token = std::env::var("TRIAS_ACCESS_TOKEN").expect("TRIAS_ACCESS_TOKEN not set");
+async fn test_relative_times() {
+    let server = MockServer::builder().fixtures().relative_times().start().await.unwrap();
+    let now = Utc::now();
+
+    let response = client(&server)
+        .stop_events("de:08212:1003".to_owned(), 10)
+        .await
+        .unwrap();
+    let event = &response.stop_event_result[0].stop_event;
+    let departure = event.this_call.call_at_stop.service_departure.as_ref().unwrap();
+    // the departure was recorded 91 seconds in the future
+    let until_departure = departure.estimated_time.unwrap() - now;
+    assert!(until_departure > Duration::seconds(60) && until_departure < Duration::seconds(120));
+    assert_eq!(event.service.operating_day_ref, now.date_naive().to_string());
+}
+
+#[tokio::test]
+async fn test_stop_events() {
+    let server = MockServer::builder()
+        .respond_matching(RequestKind::StopEvent, "de:08212:1003", fixtures::STOP_EVENT)
+        .respond(RequestKind::StopEvent, fixtures::STOP_EVENT_ERROR)
+        .start()
+        .await
+        .unwrap();
+    let client = client(&server);
+
+    let response = client.stop_events("de:08212:1003".to_owned(), 10).await.unwrap();
+    assert_eq!(response.stop_event_result.len(), 2);
+
+    let err = client.stop_events("de:08212:7".to_owned(), 10).await.unwrap_err();
+    assert!(matches!(&err, Error::Trias { code, .. } if code == "STOPEVENT_LOCATIONUNSERVED"));
+    assert!(!err.is_transient());
+}
+
+#[tokio::test]
+async fn test_trip_info() {
+    let server = MockServer::builder()
+        .respond_matching(RequestKind::TripInfo, "kvv:21002:E:H:j23:1234", fixtures::TRIP_INFO)
+        .respond(RequestKind::TripInfo, fixtures::TRIP_INFO_ERROR)
+        .start()
+        .await
+        .unwrap();
+    let client = client(&server);
+
+    let result = client
+        .trip_info("kvv:21002:E:H:j23:1234".to_owned(), "2023-10-11".to_owned())
+        .await
+        .unwrap();
+    assert!(result.current_position.is_some());
+
+    let err = client
+        .trip_info("kvv:21002:E:H:j23:1".to_owned(), "2023-10-11".to_owned())
+        .await
+        .unwrap_err();
+    assert!(matches!(&err, Error::Trias { code, .. } if code == "TRIPINFO_JOURNEYREFUNKNOWN"));
+}
+
+#[tokio::test]
+async fn test_trips() {
+    let server = MockServer::with_fixtures().await.unwrap();
+    let payload = TripRequestBuilder::new(
+        TripLocation::Stop("de:08212:7".to_owned()),
+        TripLocation::Stop("de:08212:1".to_owned()),
+    )
+    .build();
 
-    let payload = trias::StopEventRequestBuilder::new("de:08212:7".to_owned())
-        .dep_arr_time("2023-10-13T00:24:28".to_owned())
+    let trips = client(&server).trips(payload).await.unwrap();
+    assert_eq!(trips.len(), 1);
+}
+
+#[tokio::test]
+async fn test_failures() {
+    let server = MockServer::builder()
+        .fail(RequestKind::StopEvent, StatusCode::SERVICE_UNAVAILABLE)
+        .respond(RequestKind::TripInfo, fixtures::STOP_EVENT)
+        .respond(RequestKind::Trip, "<html></html>")
+        .start()
+        .await
+        .unwrap();
+    let client = client(&server);
+
+    let err = client.stop_events("de:08212:1003".to_owned(), 10).await.unwrap_err();
+    assert!(matches!(err, Error::Status(StatusCode::SERVICE_UNAVAILABLE)));
+    assert!(err.is_transient());
+
+    let err = client
+        .trip_info("kvv:21002:E:H:j23:1234".to_owned(), "2023-10-11".to_owned())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnexpectedPayload));
+
+    let payload = TripRequestBuilder::new(
+        TripLocation::Stop("de:08212:7".to_owned()),
+        TripLocation::Stop("de:08212:1".to_owned()),
+    )
+    .build();
+    assert!(matches!(client.trips(payload).await, Err(Error::Deserialize(_))));
+
+    // no response for location requests
+    let err = client.search_stops("Marktplatz".to_owned(), 1).await.unwrap_err();
+    assert!(matches!(err, Error::Status(StatusCode::NOT_IMPLEMENTED)));
+
+    drop(server);
+    let err = client.search_stops("Marktplatz".to_owned(), 1).await.unwrap_err();
+    assert!(matches!(err, Error::Transport(_)));
+}
+
+#[test]
+fn test_client_config() {
+    let client = TriasClient::new("http://127.0.0.1/trias".to_owned(), "secret-token".to_owned()).unwrap();
+    assert!(!format!("{:?}", client).contains("secret-token"));
+
+    let err = TriasClient::builder("http://127.0.0.1/trias".to_owned(), "token".to_owned())
+        .user_agent("trias\n")
         .build()
-        //... set other fields ...
-        ;
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig(_)));
+    assert!(!err.is_transient());
+}
 
-    let xml_request = trias::generate_service_request(access_token, payload).unwrap();
-    println!("{}", xml_request);
-    let response = trias::post_request(api_endpoint, &xml_request).await.unwrap();
+#[tokio::test]
+#[allow(deprecated)]
+async fn test_deprecated_functions() {
+    let server = MockServer::with_fixtures().await.unwrap();
+    let endpoint = server.endpoint();
 
-    let DeliveryPayload::StopEventResponse(response) = response.service_delivery.delivery_payload else {
-        panic!("Wrong response type");
-    };
+    let stops = trias::search_stops("de:08212:1003".to_owned(), "token".to_owned(), &endpoint, 1)
+        .await
+        .unwrap();
+    assert_eq!(stops[0].stop_point.as_ref().unwrap().stop_point_ref, "de:08212:1003");
+    let response = trias::stop_events("de:08212:1003".to_owned(), "token".to_owned(), 10, &endpoint)
+        .await
+        .unwrap();
+    assert_eq!(response.stop_event_result.len(), 2);
+    let result = trias::trip_info(
+        "kvv:21002:E:H:j23:1234".to_owned(),
+        "2023-10-11".to_owned(),
+        "token".to_owned(),
+        &endpoint,
+    )
+    .await
+    .unwrap();
+    assert!(result.current_position.is_some());
+}
+
+fn live_client() -> TriasClient {
+    dotenv::dotenv().ok();
+    let api_endpoint = std::env::var("TRIAS_API_ENDPOINT").expect("TRIAS_API_ENDPOINT not set");
+    let access_token = std::env::var("TRIAS_ACCESS_TOKEN").expect("TRIAS_ACCESS_TOKEN not set");
+    TriasClient::new(api_endpoint, access_token).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs TRIAS_API_ENDPOINT and TRIAS_ACCESS_TOKEN of a real TRIAS API"]
+async fn test_fetch_location_information() {
+    let stops = live_client()
+        .search_stops("Karlsruhe Hauptbahnhof".to_owned(), 2)
+        .await
+        .unwrap();
+    for stop in &stops {
+        println!("{:?}", stop);
+    }
+    assert!(!stops.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs TRIAS_API_ENDPOINT and TRIAS_ACCESS_TOKEN of a real TRIAS API"]
+async fn test_fetch_stop_event() {
+    let response = live_client().stop_events("de:08212:7".to_owned(), 10).await.unwrap();
 
-    let result = &response[0].stop_event_result;
+    let result = &response.stop_event_result;
     assert!(result[0].stop_event.this_call.call_at_stop.service_departure.is_some());
 }
@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use trias::response::{DeliveryPayload, TriasResponse};
+
+fn time(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+}
+
+fn parse(xml: &str) -> DeliveryPayload {
+    let response: TriasResponse = serde_xml_rs::from_str(xml).unwrap();
+    response.service_delivery.delivery_payload
+}
+
+#[test]
+fn test_location_information_fixture() {
+    let DeliveryPayload::LocationInformationResponse(response) =
+        parse(include_str!("fixtures/location_information.xml"))
+    else {
+        panic!("Wrong response type");
+    };
+    assert!(response.error_message.is_none());
+    let stops: Vec<_> = response
+        .location_result
+        .iter()
+        .map(|result| result.location.stop_point.as_ref().unwrap())
+        .collect();
+    assert_eq!(stops[0].stop_point_ref, "de:08212:1003");
+    assert_eq!(stops[0].stop_point_name.text, "Marktplatz (Kaiserstraße U)");
+    assert_eq!(stops[1].stop_point_ref, "de:08212:1011");
+    assert_eq!(response.location_result[0].location.geo_position.latitude, 49.00947);
+}
+
+#[test]
+fn test_stop_event_fixture() {
+    let DeliveryPayload::StopEventResponse(response) = parse(include_str!("fixtures/stop_event.xml")) else {
+        panic!("Wrong response type");
+    };
+    let [tram, s_bahn] = &response.stop_event_result[..] else {
+        panic!("Wrong number of results");
+    };
+
+    let event = &tram.stop_event;
+    assert_eq!(event.previous_call.len(), 1);
+    assert_eq!(event.onward_call.len(), 2);
+    let this_call = &event.this_call.call_at_stop;
+    assert_eq!(this_call.stop_point_ref, "de:08212:1003:01:01");
+    let departure = this_call.service_departure.as_ref().unwrap();
+    assert_eq!(departure.timetabled_time, time("2023-10-11T11:25:00Z"));
+    assert_eq!(departure.estimated_time, Some(time("2023-10-11T11:26:00Z")));
+    assert_eq!(event.service.journey_ref, "kvv:21002:E:H:j23:1234");
+    assert_eq!(event.service.service_section.mode.pt_mode, "tram");
+    assert_eq!(event.service.destination_text.text, "Wolfartsweier");
+    assert_eq!(event.service.attribute.len(), 1);
+
+    let event = &s_bahn.stop_event;
+    assert_eq!(event.service.service_section.published_line_name.text, "S1");
+    assert!(event
+        .this_call
+        .call_at_stop
+        .service_departure
+        .as_ref()
+        .unwrap()
+        .estimated_time
+        .is_none());
+    assert!(event
+        .onward_call
+        .last()
+        .unwrap()
+        .call_at_stop
+        .service_departure
+        .is_none());
+}
+
+#[test]
+fn test_trip_info_fixture() {
+    let DeliveryPayload::TripInfoResponse(response) = parse(include_str!("fixtures/trip_info.xml")) else {
+        panic!("Wrong response type");
+    };
+    let result = response.trip_info_result.unwrap();
+    assert_eq!(result.previous_call.len(), 1);
+    assert_eq!(result.onward_call.len(), 3);
+    assert_eq!(result.current_position.unwrap().geo_position.longitude, 8.39867);
+    assert_eq!(result.service.operating_day_ref, "2023-10-11");
+}
+
+#[test]
+fn test_error_fixtures() {
+    let DeliveryPayload::LocationInformationResponse(response) =
+        parse(include_str!("fixtures/location_information_error.xml"))
+    else {
+        panic!("Wrong response type");
+    };
+    assert!(response.location_result.is_empty());
+    assert_eq!(response.error_message.unwrap().code, "LOCATION_NORESULTS");
+
+    let DeliveryPayload::StopEventResponse(response) = parse(include_str!("fixtures/stop_event_error.xml")) else {
+        panic!("Wrong response type");
+    };
+    assert!(response.stop_event_result.is_empty());
+    assert_eq!(response.error_message.unwrap().code, "STOPEVENT_LOCATIONUNSERVED");
+
+    let DeliveryPayload::TripInfoResponse(response) = parse(include_str!("fixtures/trip_info_error.xml")) else {
+        panic!("Wrong response type");
+    };
+    assert!(response.trip_info_result.is_none());
+    assert_eq!(response.error_message.unwrap().code, "TRIPINFO_JOURNEYREFUNKNOWN");
+}
@@ -3,6 +3,9 @@
 #[cfg(test)]
 mod tests {
     use trias::generate_service_request;
+    use trias::location_information::{Circle, LocationInformationResponse};
+    use trias::response::GeoPosition;
+    use trias::GeoRestriction;
     use trias::LocationInformationRequestBuilder;
     use trias::RequestPayload;
 
@@ -14,7 +17,7 @@ mod tests {
             .build();
 
         if let RequestPayload::LocationInformationRequest(request) = payload {
-            assert_eq!(request.initial_input.location_name, "Bern");
+            assert_eq!(request.initial_input.location_name.as_deref(), Some("Bern"));
             assert_eq!(request.restrictions.location_type, "stop");
             assert_eq!(request.restrictions.number_of_results, 5);
             assert!(request.restrictions.include_pt_modes);
@@ -29,7 +32,77 @@ mod tests {
 
         let xml_output = generate_service_request("API-Explorer".to_owned(), payload).unwrap();
 
-        // assert the xml_output against the expected XML string
-        // or use it to perform an actual API request
+        assert!(xml_output.contains("<InitialInput><LocationName>Karlsruhe Hauptbahnhof</LocationName></InitialInput>"));
+        assert!(xml_output.contains("<Restrictions><Type>stop</Type>"));
+    }
+
+    #[test]
+    fn test_geo_restriction_serialization() {
+        let restriction = GeoRestriction::Circle(Circle {
+            center: GeoPosition {
+                longitude: 8.4,
+                latitude: 49.0,
+            },
+            radius: 500,
+        });
+        let payload = LocationInformationRequestBuilder::within(restriction)
+            .exclude_modes(vec!["bus".to_owned()])
+            .locality("de:08212".to_owned())
+            .number_of_results(10)
+            .build();
+
+        let xml_output = generate_service_request("API-Explorer".to_owned(), payload).unwrap();
+
+        assert!(xml_output.contains(
+            "<InitialInput><GeoRestriction><Circle><Center><Longitude>8.4</Longitude><Latitude>49</Latitude></Center><Radius>500</Radius></Circle></GeoRestriction></InitialInput>"
+        ));
+        assert!(xml_output.contains(
+            "<Restrictions><Type>stop</Type><PtModes><Exclude>true</Exclude><PtMode>bus</PtMode></PtModes><LocalityRef>de:08212</LocalityRef><NumberOfResults>10</NumberOfResults>"
+        ));
+    }
+
+    #[test]
+    fn test_location_kinds_deserialization() {
+        let xml = r#"<LocationInformationResponse>
+      <LocationResult>
+        <Location>
+          <Address>
+            <AddressCode>streetID:1500000599:4:8212000:-1:Kaiserstraße:Karlsruhe:Kaiserstraße::Kaiserstraße:76133:ANY:DIVA_STREET:940866:5744432:MRCV:B_W</AddressCode>
+            <AddressName><Text>Karlsruhe, Kaiserstraße</Text><Language>de</Language></AddressName>
+            <PostalCode>76133</PostalCode>
+            <LocalityName><Text>Karlsruhe</Text><Language>de</Language></LocalityName>
+            <LocalityRef>8212000:-1</LocalityRef>
+            <StreetName>Kaiserstraße</StreetName>
+          </Address>
+          <LocationName><Text>Karlsruhe</Text><Language>de</Language></LocationName>
+          <GeoPosition><Longitude>8.40</Longitude><Latitude>49.01</Latitude></GeoPosition>
+        </Location>
+        <Complete>true</Complete>
+        <Probability>0.8</Probability>
+      </LocationResult>
+      <LocationResult>
+        <Location>
+          <Locality>
+            <LocalityCode>8212000:-1</LocalityCode>
+            <LocalityName><Text>Karlsruhe</Text><Language>de</Language></LocalityName>
+          </Locality>
+          <LocationName><Text>Karlsruhe</Text><Language>de</Language></LocationName>
+          <GeoPosition><Longitude>8.40</Longitude><Latitude>49.01</Latitude></GeoPosition>
+        </Location>
+        <Complete>true</Complete>
+        <Probability>0.5</Probability>
+      </LocationResult>
+    </LocationInformationResponse>"#;
+
+        let response: LocationInformationResponse = serde_xml_rs::from_str(xml).unwrap();
+        let [address, locality] = &response.location_result[..] else {
+            panic!("Wrong number of results");
+        };
+        assert!(address.location.stop_point.is_none());
+        assert_eq!(
+            address.location.address.as_ref().unwrap().postal_code.as_deref(),
+            Some("76133")
+        );
+        assert_eq!(locality.location.locality.as_ref().unwrap().locality_code, "8212000:-1");
     }
 }
@@ -2,17 +2,19 @@
 
 #[cfg(test)]
 mod tests {
+    use chrono::{DateTime, Utc};
     use trias::{generate_service_request, RequestPayload, StopEventRequestBuilder}; // Adjust this import based on your library setup
 
     #[test]
     fn test_stop_event_request_builder() {
+        let time: DateTime<Utc> = "2023-10-11T11:24:28Z".parse().unwrap();
         let payload = StopEventRequestBuilder::new("8507000".to_owned())
-            .dep_arr_time("2023-10-11T11:24:28".to_owned())
+            .dep_arr_time(time)
             .build();
 
         if let RequestPayload::StopEventRequest(request) = payload {
             assert_eq!(request.location.location_ref.stop_point_ref, "8507000");
-            assert_eq!(request.location.dep_arr_time, "2023-10-11T11:24:28");
+            assert_eq!(request.location.dep_arr_time, time);
         } else {
             panic!("Wrong request type");
         }
@@ -21,12 +23,14 @@ mod tests {
     #[test]
     fn test_stop_event_request_serialization() {
         let payload = StopEventRequestBuilder::new("8507000".to_owned())
-            .dep_arr_time("2023-10-11T11:24:28".to_owned())
+            .dep_arr_time("2023-10-11T11:24:28Z".parse().unwrap())
             .build();
 
         let xml_output = generate_service_request("API-Explorer".to_owned(), payload).unwrap();
 
-        // assert the xml_output against the expected XML string
-        // or use it to perform an actual API request
+        assert!(xml_output.contains("<siri:RequestorRef>API-Explorer</siri:RequestorRef>"));
+        assert!(xml_output.contains(
+            "<Location><LocationRef><StopPointRef>8507000</StopPointRef></LocationRef><DepArrTime>2023-10-11T11:24:28Z</DepArrTime></Location>"
+        ));
     }
 }
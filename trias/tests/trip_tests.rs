@@ -0,0 +1,69 @@
+use trias::response::{DeliveryPayload, GeoPosition, TriasResponse};
+use trias::{generate_service_request, RequestPayload, TripLocation, TripRequestBuilder};
+
+#[test]
+fn test_trip_request_builder() {
+    let payload = TripRequestBuilder::new(
+        TripLocation::Stop("de:08212:7".to_owned()),
+        TripLocation::Position(GeoPosition {
+            longitude: 8.4,
+            latitude: 49.0,
+        }),
+    )
+    .via(TripLocation::Stop("de:08212:1".to_owned()))
+    .exclude_modes(vec!["bus".to_owned()])
+    .interchange_limit(2)
+    .build();
+
+    let RequestPayload::TripRequest(request) = payload else {
+        panic!("Wrong request type");
+    };
+    assert!(request.origin.dep_arr_time.is_some());
+    assert!(request.destination.dep_arr_time.is_none());
+    assert_eq!(request.via.len(), 1);
+    assert_eq!(request.params.interchange_limit, Some(2));
+    let filter = request.params.pt_mode_filter.unwrap();
+    assert!(filter.exclude);
+    assert_eq!(filter.pt_mode, ["bus"]);
+}
+
+#[test]
+fn test_trip_request_serialization() {
+    let arrival = "2023-10-11T11:24:28Z".parse().unwrap();
+    let payload = TripRequestBuilder::new(
+        TripLocation::Stop("de:08212:7".to_owned()),
+        TripLocation::Stop("de:08212:1".to_owned()),
+    )
+    .arrival_time(arrival)
+    .via(TripLocation::Stop("de:08212:3".to_owned()))
+    .include_modes(vec!["tram".to_owned(), "rail".to_owned()])
+    .build();
+
+    let xml = generate_service_request("API-Explorer".to_owned(), payload).unwrap();
+
+    assert!(xml.contains("<Origin><LocationRef><StopPointRef>de:08212:7</StopPointRef></LocationRef></Origin>"));
+    assert!(xml.contains("<DepArrTime>2023-10-11T11:24:28Z</DepArrTime>"));
+    assert!(xml.contains("<Via><ViaPoint><StopPointRef>de:08212:3</StopPointRef></ViaPoint></Via>"));
+    assert!(
+        xml.contains("<PtModeFilter><Exclude>false</Exclude><PtMode>tram</PtMode><PtMode>rail</PtMode></PtModeFilter>")
+    );
+    assert!(!xml.contains("InterchangeLimit"));
+}
+
+#[test]
+fn test_trip_response_deserialization() {
+    let xml = include_str!("fixtures/trip.xml");
+
+    let response: TriasResponse = serde_xml_rs::from_str(xml).unwrap();
+    let DeliveryPayload::TripResponse(response) = response.service_delivery.delivery_payload else {
+        panic!("Wrong response type");
+    };
+    let trip = &response.trip_result[0].trip;
+    assert_eq!(trip.trip_leg.len(), 2);
+    let ride = trip.trip_leg[0].timed_leg.as_ref().unwrap();
+    assert_eq!(ride.leg_board.stop_point_ref, "de:08212:7:01:01");
+    assert_eq!(ride.service.service_section.published_line_name.text, "Straßenbahn 2");
+    let walk = trip.trip_leg[1].continuous_leg.as_ref().unwrap();
+    assert_eq!(walk.length, Some(300));
+    assert!(walk.leg_end.geo_position.is_some());
+}